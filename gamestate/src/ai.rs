@@ -0,0 +1,226 @@
+/// What an enemy is currently doing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AiState {
+    /// Walking towards the waypoint with this index.
+    Patrol { waypoint: usize },
+    /// Going after the player, towards where they were last seen.
+    Chase {
+        last_seen: (f32, f32),
+        /// How long the player has been out of sight, in nanoseconds.
+        unseen_for: u128,
+    },
+}
+
+/// An enemy that patrols a loop of waypoints until it sees the player, then
+/// chases them until it has lost sight of them for a while.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnemyAi {
+    /// Points in world units visited in order, starting over after the last.
+    pub waypoints: Vec<(f32, f32)>,
+    /// In world units per second.
+    pub speed: f32,
+    /// How far the enemy can see, in world units.
+    pub view_distance: f32,
+    /// Width of the enemy's field of view, in degrees.
+    pub view_cone: f32,
+    /// How long the enemy keeps chasing without seeing the player, in
+    /// nanoseconds.
+    pub lose_track_after: u128,
+    /// Damage done to the player while touching them.
+    pub damage_per_second: f32,
+    pub state: AiState,
+}
+
+impl EnemyAi {
+    /// Closer than this a point counts as reached, in world units.
+    const ARRIVE_DISTANCE: f32 = 2.0;
+
+    pub fn new(waypoints: Vec<(f32, f32)>) -> EnemyAi {
+        EnemyAi {
+            waypoints,
+            speed: 60.0,
+            view_distance: 400.0,
+            view_cone: 90.0,
+            lose_track_after: 3_000_000_000,
+            damage_per_second: 20.0,
+            state: AiState::Patrol { waypoint: 0 },
+        }
+    }
+
+    /// Whether the player at `player` is inside the view cone of an enemy at
+    /// `position` looking towards `facing`. Walls are not taken into account.
+    pub(crate) fn in_view(&self, position: (f32, f32), facing: f32, player: (f32, f32)) -> bool {
+        let (dx, dy) = (player.0 - position.0, player.1 - position.1);
+        let distance = (dx * dx + dy * dy).sqrt();
+        if distance > self.view_distance {
+            return false;
+        }
+        if distance == 0.0 {
+            return true;
+        }
+        let difference = (facing_towards(dx, dy) - facing + 180.0).rem_euclid(360.0) - 180.0;
+        difference.abs() <= self.view_cone / 2.0
+    }
+
+    /// Updates the state for one tick and returns the point the enemy wants
+    /// to get to, if any.
+    pub(crate) fn think(
+        &mut self,
+        position: (f32, f32),
+        sees_player: Option<(f32, f32)>,
+        delta_time: u128,
+    ) -> Option<(f32, f32)> {
+        match (&mut self.state, sees_player) {
+            (_, Some(player)) => {
+                self.state = AiState::Chase {
+                    last_seen: player,
+                    unseen_for: 0,
+                }
+            }
+            (AiState::Chase { unseen_for, .. }, None) => {
+                *unseen_for += delta_time;
+                if *unseen_for >= self.lose_track_after {
+                    self.state = AiState::Patrol {
+                        waypoint: self.closest_waypoint(position),
+                    };
+                }
+            }
+            (AiState::Patrol { waypoint }, None) => {
+                if let Some(target) = self.waypoints.get(*waypoint) {
+                    if distance(position, *target) <= EnemyAi::ARRIVE_DISTANCE {
+                        *waypoint = (*waypoint + 1) % self.waypoints.len();
+                    }
+                }
+            }
+        }
+
+        match &self.state {
+            AiState::Patrol { waypoint } => self.waypoints.get(*waypoint).copied(),
+            AiState::Chase { last_seen, .. } => Some(*last_seen),
+        }
+    }
+
+    fn closest_waypoint(&self, position: (f32, f32)) -> usize {
+        (0..self.waypoints.len())
+            .min_by(|a, b| {
+                distance(position, self.waypoints[*a])
+                    .total_cmp(&distance(position, self.waypoints[*b]))
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// The facing, as used by the player and entities, that looks along `dx`,
+/// `dy`. Facing is the direction of a vector pointing backwards, see
+/// `Player::update_position`.
+pub(crate) fn facing_towards(dx: f32, dy: f32) -> f32 {
+    (-dy).atan2(-dx).to_degrees().rem_euclid(360.0)
+}
+
+pub(crate) fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Behaviour, Entity, EntityId, Gamestate, TickInput};
+
+    const SECOND: u128 = 1_000_000_000;
+
+    #[test]
+    fn in_view_checks_distance_and_cone() {
+        let enemy = EnemyAi::new(Vec::new());
+        let east = facing_towards(1.0, 0.0);
+        assert!(enemy.in_view((0.0, 0.0), east, (100.0, 0.0)));
+        assert!(enemy.in_view((0.0, 0.0), east, (100.0, 90.0)));
+        assert!(!enemy.in_view((0.0, 0.0), east, (100.0, 110.0)));
+        assert!(!enemy.in_view((0.0, 0.0), east, (0.0, 100.0)));
+        assert!(!enemy.in_view((0.0, 0.0), east, (-100.0, 0.0)));
+        assert!(!enemy.in_view((0.0, 0.0), east, (401.0, 0.0)));
+    }
+
+    #[test]
+    fn think_chases_on_sight_and_gives_up() {
+        let mut enemy = EnemyAi::new(vec![(0.0, 0.0), (100.0, 0.0)]);
+        let position = (90.0, 0.0);
+        assert_eq!(enemy.think(position, None, SECOND), Some((0.0, 0.0)));
+
+        let player = (50.0, 50.0);
+        assert_eq!(enemy.think(position, Some(player), SECOND), Some(player));
+        assert_eq!(
+            enemy.state,
+            AiState::Chase {
+                last_seen: player,
+                unseen_for: 0
+            }
+        );
+
+        // Keeps heading for where the player was seen for a while.
+        assert_eq!(enemy.think(position, None, 2 * SECOND), Some(player));
+        assert_eq!(enemy.think(position, None, SECOND), Some((100.0, 0.0)));
+        assert_eq!(enemy.state, AiState::Patrol { waypoint: 1 });
+    }
+
+    #[test]
+    fn think_moves_on_to_the_next_waypoint() {
+        let mut enemy = EnemyAi::new(vec![(0.0, 0.0), (100.0, 0.0)]);
+        assert_eq!(enemy.think((1.0, 0.0), None, SECOND), Some((100.0, 0.0)));
+        assert_eq!(enemy.think((100.0, 0.0), None, SECOND), Some((0.0, 0.0)));
+    }
+
+    /// A 5x3 room with an enemy at the west end of the middle row, looking
+    /// east at the player at the other end, and `middle` in between.
+    fn room(middle: char) -> (Gamestate, EntityId) {
+        let mut gamestate = Gamestate::from_map(&format!(
+            "\
+#######
+#.....#
+#..{middle}..#
+#.....#
+#######"
+        ));
+        gamestate.teleport(5, 2).unwrap();
+        let mut enemy = Entity::new((96.0, 160.0), 2);
+        enemy.facing = facing_towards(1.0, 0.0);
+        enemy.behaviour = Behaviour::Enemy(EnemyAi::new(vec![(96.0, 160.0)]));
+        let id = gamestate.spawn_entity(enemy);
+        (gamestate, id)
+    }
+
+    fn state(gamestate: &Gamestate, id: EntityId) -> AiState {
+        match &gamestate.entities().get(id).unwrap().behaviour {
+            Behaviour::Enemy(enemy) => enemy.state.clone(),
+            _ => panic!("not an enemy"),
+        }
+    }
+
+    #[test]
+    fn walls_hide_the_player() {
+        let (mut gamestate, id) = room('#');
+        gamestate.apply_input(&TickInput::new(SECOND / 10));
+        assert_eq!(state(&gamestate, id), AiState::Patrol { waypoint: 0 });
+
+        let (mut gamestate, id) = room('.');
+        gamestate.apply_input(&TickInput::new(SECOND / 10));
+        assert!(matches!(state(&gamestate, id), AiState::Chase { .. }));
+    }
+
+    #[test]
+    fn next_step_goes_around_walls() {
+        let (gamestate, _) = room('#');
+        let (from, to) = ((96.0, 160.0), (352.0, 160.0));
+        let step = gamestate.next_step(from, to).unwrap();
+        assert_ne!(step, to);
+        assert!(gamestate.has_line_of_sight(from, step));
+        assert_ne!(gamestate.cell(step), (3, 2));
+
+        // Heads straight for targets in sight.
+        assert_eq!(
+            gamestate.next_step((96.0, 96.0), (352.0, 96.0)),
+            Some((352.0, 96.0))
+        );
+        // Walls can't be reached.
+        assert_eq!(gamestate.next_step(from, (224.0, 160.0)), None);
+    }
+}
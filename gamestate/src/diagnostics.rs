@@ -0,0 +1,47 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warning,
+}
+
+/// Something noteworthy that happened inside the game logic.
+///
+/// The gamestate never prints these itself, they go to the hook set with
+/// `Gamestate::set_diagnostics_hook`, if there is one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    /// A move ran into a wall and no position along the wall could be found,
+    /// so the player was put back where they were.
+    CollisionUnresolved {
+        position: (f32, f32),
+        attempted: (f32, f32),
+    },
+}
+
+impl Diagnostic {
+    pub fn level(&self) -> Level {
+        match self {
+            Diagnostic::CollisionUnresolved { .. } => Level::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::CollisionUnresolved {
+                position,
+                attempted,
+            } => write!(
+                f,
+                "No solution found moving from {position:?} to {attempted:?}, resetting to previous position"
+            ),
+        }
+    }
+}
+
+/// Receives the diagnostics of a gamestate.
+pub type DiagnosticsHook = Box<dyn FnMut(&Diagnostic)>;
@@ -0,0 +1,315 @@
+use crate::health::Event;
+use crate::Gamestate;
+
+/// What a door is doing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DoorState {
+    Closed,
+    Opening,
+    /// Fully open for `open_for` nanoseconds.
+    Open {
+        open_for: u128,
+    },
+    Closing,
+}
+
+/// A door cell. The door is a thin slab through the middle of the cell that
+/// slides sideways into the wall next to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Door {
+    /// The key needed to open it, `None` for doors anyone can open.
+    pub key: Option<u8>,
+    /// How far it is open, from `0.0` for closed to `1.0` for fully open.
+    openness: f32,
+    state: DoorState,
+    /// Whether the slab runs along the x axis, doors are passed through
+    /// across their slab.
+    along_x: bool,
+}
+
+impl Door {
+    /// Material of door cells, see `Gamestate::map_materials`.
+    pub const MATERIAL: u8 = 10;
+    /// How long opening or closing takes, in nanoseconds.
+    pub const OPEN_TIME: u128 = 1_000_000_000;
+
+    pub(crate) fn new(key: Option<u8>, along_x: bool) -> Door {
+        Door {
+            key,
+            openness: 0.0,
+            state: DoorState::Closed,
+            along_x,
+        }
+    }
+
+    pub fn openness(&self) -> f32 {
+        self.openness
+    }
+    pub fn state(&self) -> DoorState {
+        self.state
+    }
+    pub fn along_x(&self) -> bool {
+        self.along_x
+    }
+
+    /// Only fully open doors can be walked through.
+    pub fn blocks(&self) -> bool {
+        self.openness < 1.0
+    }
+
+    /// Puts the door in a saved state. The slab's axis is saved too, since
+    /// `Map::parse` would derive it from where push-walls are now.
+    pub(crate) fn restore(&mut self, along_x: bool, openness: f32, state: DoorState) {
+        self.along_x = along_x;
+        self.openness = openness.clamp(0.0, 1.0);
+        self.state = state;
+    }
+
+    /// Advances the door by one tick. Closing doors open again if something
+    /// is in the way.
+    fn update(&mut self, delta_time: u128, occupied: bool, close_after: Option<u128>) {
+        let step = delta_time as f32 / Door::OPEN_TIME as f32;
+        match self.state {
+            DoorState::Closed => {}
+            DoorState::Opening => {
+                self.openness = (self.openness + step).min(1.0);
+                if self.openness >= 1.0 {
+                    self.state = DoorState::Open { open_for: 0 };
+                }
+            }
+            DoorState::Open { open_for } => {
+                let open_for = open_for + delta_time;
+                self.state = match close_after {
+                    Some(close_after) if open_for >= close_after && !occupied => DoorState::Closing,
+                    _ => DoorState::Open { open_for },
+                };
+            }
+            DoorState::Closing if occupied => self.state = DoorState::Opening,
+            DoorState::Closing => {
+                self.openness = (self.openness - step).max(0.0);
+                if self.openness <= 0.0 {
+                    self.state = DoorState::Closed;
+                }
+            }
+        }
+    }
+}
+
+impl Gamestate {
+    /// How long doors stay open before closing on their own, in nanoseconds,
+    /// if they do.
+    pub const DOOR_CLOSE_AFTER: u128 = 5_000_000_000;
+    /// How far in front of the player doors and push-walls can be used, in
+    /// world units.
+    const USE_DISTANCE: f32 = 48.0;
+
+    /// The door in a cell, if it is one.
+    pub fn door(&self, x: u32, y: u32) -> Option<&Door> {
+        if x >= self.map_width() as u32 || y >= self.map_height() as u32 {
+            return None;
+        }
+        self.map.door((y * self.map_width() as u32 + x) as u16)
+    }
+
+    /// Every door with the id of its cell, `y * width + x`.
+    pub fn doors(&self) -> Vec<(u16, &Door)> {
+        self.map
+            .door_cells()
+            .into_iter()
+            .filter_map(|cell| Some((cell, self.map.door(cell)?)))
+            .collect()
+    }
+
+    pub fn doors_close_after(&self) -> Option<u128> {
+        self.doors_close_after
+    }
+    /// `None` keeps doors open until they are used again.
+    pub fn set_doors_close_after(&mut self, close_after: Option<u128>) {
+        self.doors_close_after = close_after;
+    }
+
+    /// Opens or closes the door right in front of the player. Locked doors
+    /// only open if the player carries their key.
+    pub fn use_door(&mut self) {
+        let Some(cell) = self.cell_in_front() else {
+            return;
+        };
+
+        let occupied = self.occupied(cell);
+        let carried_key = |key: u8| self.player.inventory().has_key(key);
+        let Some(door) = self.map.door(cell) else {
+            return;
+        };
+        let state = match door.state {
+            DoorState::Closed | DoorState::Closing => match door.key {
+                Some(key) if !carried_key(key) => {
+                    self.events.push(Event::DoorLocked { key });
+                    return;
+                }
+                _ => DoorState::Opening,
+            },
+            DoorState::Opening | DoorState::Open { .. } if !occupied => DoorState::Closing,
+            DoorState::Opening | DoorState::Open { .. } => return,
+        };
+        if let Some(door) = self.map.door_mut(cell) {
+            door.state = state;
+        }
+    }
+
+    /// Moves every door that is opening or closing.
+    pub(crate) fn update_doors(&mut self, delta_time: u128) {
+        let occupied = self
+            .map
+            .door_cells()
+            .into_iter()
+            .filter(|cell| self.occupied(*cell))
+            .collect::<Vec<u16>>();
+        let close_after = self.doors_close_after;
+        for cell in self.map.door_cells() {
+            if let Some(door) = self.map.door_mut(cell) {
+                door.update(delta_time, occupied.contains(&cell), close_after);
+            }
+        }
+    }
+
+    /// Where a ray from `origin` along the unit vector `direction` meets the
+    /// closed part of the door in `cell`, as the distance from `origin`.
+    pub(crate) fn door_hit(
+        &self,
+        cell: u16,
+        origin: (f32, f32),
+        (cos, sin): (f32, f32),
+    ) -> Option<f32> {
+        let door = self.map.door(cell)?;
+        let (left, top, _, _) = self.block_corners(cell);
+        let block_size = self.block_size as f32;
+
+        // Distance to the slab and how far along it the ray meets it.
+        let (distance, along) = if door.along_x {
+            let distance = (top + block_size / 2.0 - origin.1) / sin;
+            (distance, origin.0 + cos * distance - left)
+        } else {
+            let distance = (left + block_size / 2.0 - origin.0) / cos;
+            (distance, origin.1 + sin * distance - top)
+        };
+        // The open part of the door is at the start of the slab.
+        let closed = (door.openness * block_size)..block_size;
+        (distance.is_finite() && distance >= 0.0 && closed.contains(&along)).then_some(distance)
+    }
+
+    /// The first cell other than their own in front of the player, if one
+    /// is within `USE_DISTANCE`.
+    pub(crate) fn cell_in_front(&self) -> Option<u16> {
+        const STEP: f32 = 4.0;
+        let (x, y) = self.player.position();
+        let (sin, cos) = self.player.facing().to_radians().sin_cos();
+        let own_cell = self.block_id(x, y);
+        (1..=(Gamestate::USE_DISTANCE / STEP) as u32)
+            .map(|step| (x + cos * step as f32 * STEP, y + sin * step as f32 * STEP))
+            .take_while(|(x, y)| self.inside_map(*x, *y))
+            .map(|(x, y)| self.block_id(x, y))
+            .find(|cell| *cell != own_cell)
+    }
+
+    /// Whether the player or an entity is in a cell.
+    pub(crate) fn occupied(&self, cell: u16) -> bool {
+        let (x, y) = self.player.position();
+        let width = self.map_width() as u32;
+        let (column, row) = (cell as u32 % width, cell as u32 / width);
+        self.block_id(x, y) == cell || !self.entities_in_cell(column, row).is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Entity;
+
+    /// A corridor with the door at (3, 1) and the player right in front of
+    /// it, looking at it.
+    fn corridor(door: char) -> Gamestate {
+        let mut gamestate = Gamestate::from_map(&format!(
+            "\
+#######
+#.P{door}..#
+#######"
+        ));
+        gamestate.player.set_view_direction(180.0);
+        gamestate
+    }
+
+    fn door(gamestate: &Gamestate) -> &Door {
+        gamestate.door(3, 1).unwrap()
+    }
+
+    #[test]
+    fn locked_doors_only_open_with_their_key() {
+        let mut gamestate = corridor('a');
+        gamestate.use_door();
+        assert_eq!(door(&gamestate).state(), DoorState::Closed);
+        assert_eq!(gamestate.take_events(), vec![Event::DoorLocked { key: 1 }]);
+
+        gamestate.inventory_mut().keys.insert(1);
+        gamestate.use_door();
+        assert_eq!(door(&gamestate).state(), DoorState::Opening);
+        assert!(gamestate.take_events().is_empty());
+    }
+
+    #[test]
+    fn open_doors_close_after_the_delay() {
+        let mut gamestate = corridor('D');
+        gamestate.use_door();
+        gamestate.update_doors(Door::OPEN_TIME);
+        assert_eq!(door(&gamestate).state(), DoorState::Open { open_for: 0 });
+        assert!(!door(&gamestate).blocks());
+
+        gamestate.update_doors(Gamestate::DOOR_CLOSE_AFTER - 1);
+        assert!(matches!(door(&gamestate).state(), DoorState::Open { .. }));
+        gamestate.update_doors(1);
+        assert_eq!(door(&gamestate).state(), DoorState::Closing);
+        gamestate.update_doors(Door::OPEN_TIME);
+        assert_eq!(door(&gamestate).state(), DoorState::Closed);
+        assert_eq!(door(&gamestate).openness(), 0.0);
+    }
+
+    #[test]
+    fn occupied_doors_stay_open_and_reopen() {
+        let mut gamestate = corridor('D');
+        gamestate.use_door();
+        gamestate.update_doors(Door::OPEN_TIME);
+
+        let id = gamestate.spawn_entity(Entity::new((224.0, 96.0), 0));
+        gamestate.update_doors(Gamestate::DOOR_CLOSE_AFTER * 2);
+        gamestate.use_door();
+        assert!(matches!(door(&gamestate).state(), DoorState::Open { .. }));
+
+        gamestate.despawn_entity(id);
+        gamestate.teleport(3, 1).unwrap();
+        gamestate.update_doors(1);
+        assert!(matches!(door(&gamestate).state(), DoorState::Open { .. }));
+
+        gamestate.teleport(2, 1).unwrap();
+        gamestate.update_doors(1);
+        gamestate.update_doors(Door::OPEN_TIME / 2);
+        assert_eq!(door(&gamestate).state(), DoorState::Closing);
+        gamestate.spawn_entity(Entity::new((224.0, 96.0), 0));
+        gamestate.update_doors(1);
+        assert_eq!(door(&gamestate).state(), DoorState::Opening);
+    }
+
+    #[test]
+    fn rays_pass_the_open_part_of_a_door() {
+        let mut gamestate = corridor('D');
+        gamestate.use_door();
+        gamestate.update_doors(Door::OPEN_TIME / 2);
+        assert_eq!(door(&gamestate).openness(), 0.5);
+
+        // The door slides towards the top of the cell, y 64 to 96 is open.
+        let (x, _, hit) = gamestate.cast_ray((160.0, 80.0), 0.0, 400.0);
+        assert!(hit && x > 380.0, "{x}");
+        assert_eq!(
+            gamestate.cast_ray((160.0, 112.0), 0.0, 400.0),
+            (224.0, 112.0, true)
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+
+use crate::ai::EnemyAi;
+use crate::item::Item;
+
+/// Identifies an entity for as long as it exists. Ids are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId(pub u32);
+
+/// What an entity does on its own every tick.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Behaviour {
+    /// Stays as it is, like most props.
+    Static,
+    /// Turns in place, positive speeds turning the same way as the player does
+    /// when turning right.
+    Spin { degrees_per_second: f32 },
+    /// Patrols and chases the player. Moved by `Gamestate::apply_input`, since
+    /// it needs the map and the player.
+    Enemy(EnemyAi),
+    /// Lies around until the player walks over it.
+    Pickup(Item),
+}
+
+/// Anything in the world besides the player and the walls.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    /// In world units.
+    pub position: (f32, f32),
+    /// In degrees, like the player's view direction.
+    pub facing: f32,
+    /// Size of the entity for collisions and queries, in world units.
+    pub radius: f32,
+    /// Taken down by weapons, the entity is despawned once it reaches zero.
+    /// `None` for entities that can't be hurt.
+    pub health: Option<f32>,
+    /// Which sprite the renderer should show for it.
+    pub sprite: u16,
+    pub behaviour: Behaviour,
+}
+
+impl Entity {
+    pub const DEFAULT_RADIUS: f32 = 20.0;
+
+    /// A static entity with the default radius that can't be hurt, facing the
+    /// same way as a new player.
+    pub fn new(position: (f32, f32), sprite: u16) -> Entity {
+        Entity {
+            position,
+            facing: 90.0,
+            radius: Entity::DEFAULT_RADIUS,
+            health: None,
+            sprite,
+            behaviour: Behaviour::Static,
+        }
+    }
+
+    fn update(&mut self, delta_time: u128) {
+        match &self.behaviour {
+            Behaviour::Static | Behaviour::Enemy(_) | Behaviour::Pickup(_) => {}
+            Behaviour::Spin { degrees_per_second } => {
+                let seconds = delta_time as f32 / 1_000_000_000.0;
+                self.facing = (self.facing + degrees_per_second * seconds).rem_euclid(360.0);
+            }
+        }
+    }
+}
+
+/// All entities in the world, kept in the order they were spawned so that
+/// updates happen in the same order every time a recording is replayed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Entities {
+    next_id: u32,
+    entities: BTreeMap<EntityId, Entity>,
+}
+
+impl Entities {
+    pub fn new() -> Entities {
+        Entities::default()
+    }
+
+    pub fn spawn(&mut self, entity: Entity) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        self.entities.insert(id, entity);
+        id
+    }
+
+    /// Removes an entity, returning it if it existed.
+    pub fn despawn(&mut self, id: EntityId) -> Option<Entity> {
+        self.entities.remove(&id)
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &Entity)> {
+        self.entities.iter().map(|(id, entity)| (*id, entity))
+    }
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Entities touching the circle around `point`, counting their own radius.
+    pub fn within_radius(&self, point: (f32, f32), radius: f32) -> Vec<EntityId> {
+        self.iter()
+            .filter(|(_, entity)| {
+                let (dx, dy) = (entity.position.0 - point.0, entity.position.1 - point.1);
+                (dx * dx + dy * dy).sqrt() <= radius + entity.radius
+            })
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Entities whose center is in the given cell.
+    pub fn in_cell(&self, x: u32, y: u32, block_size: u16) -> Vec<EntityId> {
+        let block_size = block_size as f32;
+        self.iter()
+            .filter(|(_, entity)| {
+                let (entity_x, entity_y) = entity.position;
+                entity_x >= 0.0
+                    && entity_y >= 0.0
+                    && (entity_x / block_size) as u32 == x
+                    && (entity_y / block_size) as u32 == y
+            })
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Runs the behaviour of every entity for one tick.
+    pub fn update(&mut self, delta_time: u128) {
+        for entity in self.entities.values_mut() {
+            entity.update(delta_time);
+        }
+    }
+
+    /// The id the next spawned entity will get, see `restore`.
+    pub(crate) fn next_id(&self) -> u32 {
+        self.next_id
+    }
+
+    /// Rebuilds the store from saved entities, keeping their ids.
+    pub(crate) fn restore(next_id: u32, entities: Vec<(EntityId, Entity)>) -> Entities {
+        Entities {
+            next_id,
+            entities: entities.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_count_up_and_are_never_reused() {
+        let mut entities = Entities::new();
+        let first = entities.spawn(Entity::new((10.0, 10.0), 1));
+        let second = entities.spawn(Entity::new((20.0, 20.0), 2));
+        assert_eq!((first, second), (EntityId(0), EntityId(1)));
+
+        assert_eq!(entities.despawn(first).map(|entity| entity.sprite), Some(1));
+        assert_eq!(entities.despawn(first), None);
+        assert_eq!(entities.spawn(Entity::new((30.0, 30.0), 3)), EntityId(2));
+        assert_eq!(entities.len(), 2);
+        assert!(entities.get(first).is_none());
+        assert_eq!(entities.get(second).map(|entity| entity.sprite), Some(2));
+    }
+
+    #[test]
+    fn queries_by_radius_and_cell() {
+        let mut entities = Entities::new();
+        let near = entities.spawn(Entity::new((100.0, 100.0), 0));
+        let mut small = Entity::new((150.0, 100.0), 0);
+        small.radius = 5.0;
+        let far = entities.spawn(small);
+        let outside = entities.spawn(Entity::new((-10.0, 100.0), 0));
+
+        // Touching counts the entity's own radius.
+        assert_eq!(entities.within_radius((100.0, 100.0), 0.0), vec![near]);
+        assert_eq!(
+            entities.within_radius((120.0, 100.0), 25.0),
+            vec![near, far]
+        );
+        assert!(entities.within_radius((300.0, 300.0), 10.0).is_empty());
+
+        assert_eq!(entities.in_cell(1, 1, 64), vec![near]);
+        assert_eq!(entities.in_cell(2, 1, 64), vec![far]);
+        // Negative positions are in no cell, rather than rounding into 0.
+        assert!(entities.in_cell(0, 1, 64).is_empty());
+        assert_eq!(entities.within_radius((-10.0, 100.0), 0.0), vec![outside]);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::entity::EntityId;
+use crate::item::Item;
+use crate::player::Player;
+use crate::Gamestate;
+
+/// Who or what took damage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Player,
+    Entity(EntityId),
+}
+
+/// Where damage came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageSource {
+    /// The player's weapon.
+    Player,
+    /// An enemy touching the player.
+    Enemy(EntityId),
+    /// Anything else, like the console.
+    World,
+}
+
+/// Something that happened to the player or an entity during a tick, for the
+/// renderer and the sound to react to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /// `amount` is the damage dealt, before the player's armor took its share.
+    Damaged {
+        target: Target,
+        amount: f32,
+        source: DamageSource,
+    },
+    /// A dead entity is already despawned, the player respawns after
+    /// `Gamestate::RESPAWN_DELAY`.
+    Died {
+        target: Target,
+        source: DamageSource,
+    },
+    Respawned,
+    /// The player picked up an item, its entity is already despawned.
+    PickedUp {
+        id: EntityId,
+        item: Item,
+    },
+    /// The player tried to open a door without its key.
+    DoorLocked {
+        key: u8,
+    },
+}
+
+impl Gamestate {
+    /// How long the player stays dead, in nanoseconds.
+    pub const RESPAWN_DELAY: u128 = 2_000_000_000;
+    /// Share of the damage to the player that armor takes instead, as long as
+    /// there is armor left.
+    pub const ARMOR_ABSORPTION: f32 = 0.5;
+
+    /// The events since this was last called, oldest first.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn player_health(&self) -> f32 {
+        self.player.health()
+    }
+    pub fn set_player_health(&mut self, health: f32) {
+        self.player
+            .set_health(health.clamp(0.0, Player::MAX_HEALTH));
+    }
+    pub fn player_armor(&self) -> f32 {
+        self.player.armor()
+    }
+    pub fn set_player_armor(&mut self, armor: f32) {
+        self.player.set_armor(armor.max(0.0));
+    }
+    pub fn player_alive(&self) -> bool {
+        self.respawn_in.is_none()
+    }
+
+    /// Where the player respawns, the center of the level's start cell.
+    pub fn spawn_point(&self) -> (f32, f32) {
+        self.spawn_point
+    }
+
+    /// Hurts the player, armor taking `ARMOR_ABSORPTION` of the damage while
+    /// it lasts. Does nothing while the player is dead.
+    pub fn damage_player(&mut self, amount: f32, source: DamageSource) {
+        if !self.player_alive() || amount <= 0.0 {
+            return;
+        }
+
+        let absorbed = (amount * Gamestate::ARMOR_ABSORPTION).min(self.player.armor());
+        self.player.set_armor(self.player.armor() - absorbed);
+        let health = (self.player.health() - (amount - absorbed)).max(0.0);
+        self.player.set_health(health);
+        self.events.push(Event::Damaged {
+            target: Target::Player,
+            amount,
+            source,
+        });
+
+        if health <= 0.0 {
+            self.respawn_in = Some(Gamestate::RESPAWN_DELAY);
+            self.events.push(Event::Died {
+                target: Target::Player,
+                source,
+            });
+        }
+    }
+
+    /// Hurts an entity that has health and despawns it once that runs out.
+    /// Returns whether it died.
+    pub fn damage_entity(&mut self, id: EntityId, amount: f32, source: DamageSource) -> bool {
+        let Some(health) = self
+            .entities
+            .get_mut(id)
+            .and_then(|entity| entity.health.as_mut())
+        else {
+            return false;
+        };
+
+        *health -= amount;
+        let died = *health <= 0.0;
+        self.events.push(Event::Damaged {
+            target: Target::Entity(id),
+            amount,
+            source,
+        });
+        if died {
+            self.entities.despawn(id);
+            self.events.push(Event::Died {
+                target: Target::Entity(id),
+                source,
+            });
+        }
+        died
+    }
+
+    /// Counts down to the respawn of a dead player and respawns them once it
+    /// is time.
+    pub(crate) fn update_respawn(&mut self, delta_time: u128) {
+        let Some(respawn_in) = self.respawn_in else {
+            return;
+        };
+        if respawn_in > delta_time {
+            self.respawn_in = Some(respawn_in - delta_time);
+            return;
+        }
+
+        self.respawn_in = None;
+        let (x, y) = self.spawn_point;
+        self.player.set_position(x, y);
+        self.player.set_view_direction(90.0);
+        self.player.set_health(Player::MAX_HEALTH);
+        self.player.set_armor(0.0);
+        self.events.push(Event::Respawned);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Entity;
+
+    fn game() -> Gamestate {
+        Gamestate::from_map(
+            "\
+#####
+#P..#
+#####",
+        )
+    }
+
+    #[test]
+    fn armor_takes_half_until_it_runs_out() {
+        let mut gamestate = game();
+        gamestate.set_player_armor(15.0);
+        gamestate.damage_player(20.0, DamageSource::World);
+        assert_eq!(gamestate.player_armor(), 5.0);
+        assert_eq!(gamestate.player_health(), Player::MAX_HEALTH - 10.0);
+
+        gamestate.damage_player(20.0, DamageSource::World);
+        assert_eq!(gamestate.player_armor(), 0.0);
+        assert_eq!(gamestate.player_health(), Player::MAX_HEALTH - 25.0);
+        assert_eq!(
+            gamestate.take_events(),
+            vec![
+                Event::Damaged {
+                    target: Target::Player,
+                    amount: 20.0,
+                    source: DamageSource::World
+                };
+                2
+            ]
+        );
+    }
+
+    #[test]
+    fn the_player_dies_at_zero_health_and_respawns_later() {
+        let mut gamestate = game();
+        let enemy = DamageSource::Enemy(EntityId(7));
+        gamestate.teleport(3, 1).unwrap();
+        gamestate.damage_player(Player::MAX_HEALTH + 1.0, enemy);
+        assert_eq!(gamestate.player_health(), 0.0);
+        assert!(!gamestate.player_alive());
+        assert_eq!(
+            gamestate.take_events(),
+            vec![
+                Event::Damaged {
+                    target: Target::Player,
+                    amount: Player::MAX_HEALTH + 1.0,
+                    source: enemy
+                },
+                Event::Died {
+                    target: Target::Player,
+                    source: enemy
+                },
+            ]
+        );
+
+        // The dead don't take damage.
+        gamestate.damage_player(10.0, enemy);
+        assert!(gamestate.take_events().is_empty());
+
+        gamestate.update_respawn(Gamestate::RESPAWN_DELAY - 1);
+        assert!(!gamestate.player_alive());
+        gamestate.update_respawn(1);
+        assert!(gamestate.player_alive());
+        assert_eq!(gamestate.player_position(), gamestate.spawn_point());
+        assert_eq!(gamestate.spawn_point(), (96.0, 96.0));
+        assert_eq!(gamestate.player_health(), Player::MAX_HEALTH);
+        assert_eq!(gamestate.take_events(), vec![Event::Respawned]);
+    }
+
+    #[test]
+    fn entities_with_health_die_and_despawn() {
+        let mut gamestate = game();
+        let mut target = Entity::new((160.0, 96.0), 0);
+        target.health = Some(30.0);
+        let id = gamestate.spawn_entity(target);
+        let prop = gamestate.spawn_entity(Entity::new((224.0, 96.0), 0));
+
+        assert!(!gamestate.damage_entity(prop, 50.0, DamageSource::Player));
+        assert!(!gamestate.damage_entity(id, 20.0, DamageSource::Player));
+        assert_eq!(gamestate.entities().get(id).unwrap().health, Some(10.0));
+        assert!(gamestate.damage_entity(id, 20.0, DamageSource::Player));
+        assert!(gamestate.entities().get(id).is_none());
+        assert!(gamestate.entities().get(prop).is_some());
+
+        let damaged = |amount| Event::Damaged {
+            target: Target::Entity(id),
+            amount,
+            source: DamageSource::Player,
+        };
+        assert_eq!(
+            gamestate.take_events(),
+            vec![
+                damaged(20.0),
+                damaged(20.0),
+                Event::Died {
+                    target: Target::Entity(id),
+                    source: DamageSource::Player
+                },
+            ]
+        );
+    }
+}
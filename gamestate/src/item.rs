@@ -0,0 +1,92 @@
+use std::collections::BTreeSet;
+
+use crate::ai;
+use crate::entity::{Behaviour, EntityId};
+use crate::health::Event;
+use crate::player::Player;
+use crate::Gamestate;
+
+/// Something the player can pick up by walking over it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Item {
+    /// Heals the player, left lying around while they are at full health.
+    Health(f32),
+    /// Rounds for the weapon.
+    Ammo(u32),
+    /// Opens the doors locked with the same key.
+    Key(u8),
+    /// Points added to the score.
+    Score(u32),
+}
+
+/// What the player carries besides health and ammo.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Inventory {
+    pub keys: BTreeSet<u8>,
+    pub score: u32,
+}
+
+impl Inventory {
+    pub fn new() -> Inventory {
+        Inventory::default()
+    }
+
+    pub fn has_key(&self, key: u8) -> bool {
+        self.keys.contains(&key)
+    }
+}
+
+impl Gamestate {
+    pub fn inventory(&self) -> &Inventory {
+        self.player.inventory()
+    }
+    pub fn inventory_mut(&mut self) -> &mut Inventory {
+        self.player.inventory_mut()
+    }
+
+    /// Gives the player every pickup they overlap and despawns it.
+    pub(crate) fn update_pickups(&mut self) {
+        if !self.player_alive() {
+            return;
+        }
+
+        let player = self.player.position();
+        let touched = self
+            .entities
+            .iter()
+            .filter_map(|(id, entity)| match entity.behaviour {
+                Behaviour::Pickup(item)
+                    if ai::distance(entity.position, player) <= entity.radius =>
+                {
+                    Some((id, item))
+                }
+                _ => None,
+            })
+            .collect::<Vec<(EntityId, Item)>>();
+
+        for (id, item) in touched {
+            if self.pick_up(item) {
+                self.entities.despawn(id);
+                self.events.push(Event::PickedUp { id, item });
+            }
+        }
+    }
+
+    /// Applies an item to the player, returning whether it was used.
+    fn pick_up(&mut self, item: Item) -> bool {
+        match item {
+            Item::Health(amount) => {
+                if self.player.health() >= Player::MAX_HEALTH {
+                    return false;
+                }
+                self.set_player_health(self.player.health() + amount);
+            }
+            Item::Ammo(rounds) => self.weapon.ammo += rounds,
+            Item::Key(key) => {
+                self.player.inventory_mut().keys.insert(key);
+            }
+            Item::Score(points) => self.player.inventory_mut().score += points,
+        }
+        true
+    }
+}
@@ -1,14 +1,55 @@
+mod ai;
+mod diagnostics;
+mod door;
+mod entity;
+mod health;
+mod item;
 mod map;
+mod path;
 mod player;
+mod push_wall;
+mod recording;
+mod rng;
+mod save;
+mod weapon;
 
 use crate::map::Map;
 use crate::player::Player;
+use crate::rng::Rng;
+use std::path::Path;
+
+pub use crate::ai::{AiState, EnemyAi};
+pub use crate::diagnostics::{Diagnostic, DiagnosticsHook, Level};
+pub use crate::door::{Door, DoorState};
+pub use crate::entity::{Behaviour, Entities, Entity, EntityId};
+pub use crate::health::{DamageSource, Event, Target};
+pub use crate::item::{Inventory, Item};
+pub use crate::path::Neighbours;
+pub use crate::push_wall::{PushWall, PushWallState};
+pub use crate::recording::{Action, Recording, TickInput};
+pub use crate::weapon::{Hit, Shot, Weapon};
 #[allow(dead_code)]
 pub struct Gamestate {
     map: Map,
     player: Player,
+    entities: Entities,
     block_size: u16,
     ray_angles: Vec<f32>,
+    seed: u64,
+    /// Everything random in the game, started from the seed.
+    rng: Rng,
+    weapon: Weapon,
+    /// Shots fired during ticks and not yet taken, see `take_shots`.
+    shots: Vec<Shot>,
+    /// Events from ticks not yet taken, see `take_events`.
+    events: Vec<Event>,
+    spawn_point: (f32, f32),
+    /// Time until a dead player respawns, `None` while they are alive.
+    respawn_in: Option<u128>,
+    doors_close_after: Option<u128>,
+    /// Lets the player walk through walls, but not off the map.
+    noclip: bool,
+    diagnostics_hook: Option<DiagnosticsHook>,
 }
 
 impl Gamestate {
@@ -23,19 +64,54 @@ impl Gamestate {
         let map = Map::new(map_matrix);
         let player = player::Player::new(player_x, player_y, Gamestate::PLAYER_FOV, 150);
 
-        let ray_angles = (1..)
-            .map(|v| {
-                (Gamestate::PLAYER_FOV / (ray_count + 1) as f32) * (v as f32)
-                    + (Gamestate::PLAYER_FOV)
-            })
-            .take(ray_count as usize)
-            .collect::<Vec<f32>>();
+        let ray_angles = Gamestate::ray_angles(Gamestate::PLAYER_FOV, ray_count);
 
         Gamestate {
             map,
             player,
+            entities: Entities::new(),
             block_size,
             ray_angles,
+            seed: 0,
+            rng: Rng::new(0),
+            weapon: Weapon::default(),
+            shots: Vec::new(),
+            events: Vec::new(),
+            spawn_point: (player_x, player_y),
+            respawn_in: None,
+            doors_close_after: Some(Gamestate::DOOR_CLOSE_AFTER),
+            noclip: false,
+            diagnostics_hook: None,
+        }
+    }
+
+    /// Angles of the rays relative to the player's view direction vector,
+    /// which points backwards, see `Player::update_position`.
+    fn ray_angles(fov: f32, ray_count: u16) -> Vec<f32> {
+        (1..)
+            .map(|v| (fov / (ray_count + 1) as f32) * (v as f32) + (180.0 - fov / 2.0))
+            .take(ray_count as usize)
+            .collect::<Vec<f32>>()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Also restarts the random numbers, so the same seed always gives the
+    /// same game.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
+    }
+
+    /// Sends every diagnostic from now on to `hook`, instead of dropping them.
+    pub fn set_diagnostics_hook(&mut self, hook: impl FnMut(&Diagnostic) + 'static) {
+        self.diagnostics_hook = Some(Box::new(hook));
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        if let Some(hook) = self.diagnostics_hook.as_mut() {
+            hook(&diagnostic);
         }
     }
 
@@ -52,6 +128,218 @@ impl Gamestate {
         self.block_size
     }
 
+    /// Replaces the map with one read from a file, see `Map::parse` for the
+    /// format. The player is moved to the map's start cell, or the first floor
+    /// cell if it has none, and the explored cells and entities are forgotten.
+    /// The map's markers are spawned as new entities.
+    pub fn load_map(&mut self, path: &Path) -> Result<(), String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        self.set_map(&text)
+    }
+
+    fn set_map(&mut self, text: &str) -> Result<(), String> {
+        let (map, start, markers) = Map::parse(text)?;
+
+        let width = map.width() as usize;
+        let start = match start {
+            Some(start) => start,
+            None => (0..map.width() as usize * map.height() as usize)
+                .find(|cell| !map.blocks(*cell as u16))
+                .map(|cell| ((cell % width) as u8, (cell / width) as u8))
+                .ok_or("The map has no floor to stand on")?,
+        };
+
+        self.map = map;
+        self.entities = Entities::new();
+        for ((x, y), marker) in markers {
+            let position = self.cell_center((x as u32, y as u32));
+            self.entities.spawn(marker.entity(position));
+        }
+        self.place_player(start.0 as u32, start.1 as u32);
+        self.spawn_point = self.player.position();
+        Ok(())
+    }
+
+    pub fn field_of_view(&self) -> f32 {
+        self.player.field_of_view()
+    }
+    pub fn set_field_of_view(&mut self, fov: f32) -> Result<(), String> {
+        if !(1.0..=360.0).contains(&fov) {
+            return Err(format!(
+                "Field of view has to be between 1 and 360, got {fov}"
+            ));
+        }
+        self.player.set_field_of_view(fov);
+        self.ray_angles = Gamestate::ray_angles(fov, self.ray_count());
+        Ok(())
+    }
+    pub fn ray_count(&self) -> u16 {
+        self.ray_angles.len() as u16
+    }
+    pub fn set_ray_count(&mut self, ray_count: u16) -> Result<(), String> {
+        if ray_count == 0 {
+            return Err("At least one ray is needed".to_string());
+        }
+        self.ray_angles = Gamestate::ray_angles(self.field_of_view(), ray_count);
+        Ok(())
+    }
+
+    pub fn noclip(&self) -> bool {
+        self.noclip
+    }
+    pub fn set_noclip(&mut self, noclip: bool) {
+        self.noclip = noclip;
+    }
+
+    /// Moves the player to the middle of a cell. Walls are only allowed with
+    /// noclip on.
+    pub fn teleport(&mut self, x: u32, y: u32) -> Result<(), String> {
+        if x >= self.map_width() as u32 || y >= self.map_height() as u32 {
+            return Err(format!(
+                "Cell {x} {y} is outside of the {}x{} map",
+                self.map_width(),
+                self.map_height()
+            ));
+        }
+        let cell = (y * self.map_width() as u32 + x) as u16;
+        if !self.noclip && self.map.blocks(cell) {
+            return Err(match self.map.door(cell) {
+                Some(_) => format!("Cell {x} {y} is a closed door"),
+                None => format!("Cell {x} {y} is a wall"),
+            });
+        }
+        self.place_player(x, y);
+        Ok(())
+    }
+
+    fn place_player(&mut self, x: u32, y: u32) {
+        let block_size = self.block_size as f32;
+        self.player
+            .set_position((x as f32 + 0.5) * block_size, (y as f32 + 0.5) * block_size);
+    }
+
+    /// One entry per cell, the material of each wall, `0` for plain walls and
+    /// floor.
+    pub fn map_materials(&self) -> &Vec<u8> {
+        self.map.materials()
+    }
+
+    /// The material of the wall each ray ended on, `0` for rays that did not
+    /// hit anything.
+    pub fn hit_materials(&self, rays: &[(f32, f32, bool)]) -> Vec<u8> {
+        let (player_x, player_y) = self.player.position();
+        rays.iter()
+            .map(|(x, y, hits_wall)| {
+                let length = ((x - player_x).powi(2) + (y - player_y).powi(2)).sqrt();
+                if !hits_wall || length == 0.0 {
+                    return 0;
+                }
+                // Rays stop just short of the wall, see `ray_wall_collision`.
+                let scale = (length + 10.0) / length;
+                let (wall_x, wall_y) = (
+                    player_x + (x - player_x) * scale,
+                    player_y + (y - player_y) * scale,
+                );
+                if !self.inside_map(wall_x, wall_y) {
+                    return 0;
+                }
+                let cell = self.block_id(wall_x, wall_y) as usize;
+                self.map.materials().get(cell).copied().unwrap_or(0)
+            })
+            .collect()
+    }
+
+    /// One entry per cell, `true` for the cells the player has seen so far.
+    pub fn explored_cells(&self) -> &Vec<bool> {
+        self.map.explored()
+    }
+    pub fn set_explored_cells(&mut self, explored: Vec<bool>) -> Result<(), String> {
+        self.map.set_explored(explored)
+    }
+
+    /// Marks every cell the rays from `cast_rays` passed through as explored,
+    /// including the walls they hit.
+    pub fn explore(&mut self, rays: &[(f32, f32, bool)]) {
+        let (player_x, player_y) = self.player.position();
+        if self.inside_map(player_x, player_y) {
+            self.map.explore(self.block_id(player_x, player_y));
+        }
+
+        let step = self.block_size as f32 / 4.0;
+        for (ray_x, ray_y, hits_wall) in rays {
+            let (x_diff, y_diff) = (ray_x - player_x, ray_y - player_y);
+            let length = (x_diff.powi(2) + y_diff.powi(2)).sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            let (cos, sin) = (x_diff / length, y_diff / length);
+
+            // Rays stop right before the wall they hit, so look a bit further
+            // to include it, the same distance `ray_wall_collision` looks ahead.
+            let end = if *hits_wall { length + 10.0 } else { length };
+            let mut dist = 0.0;
+            loop {
+                let (x, y) = (cos * dist + player_x, sin * dist + player_y);
+                if self.inside_map(x, y) {
+                    self.map.explore(self.block_id(x, y));
+                }
+                if dist >= end {
+                    break;
+                }
+                dist = (dist + step).min(end);
+            }
+        }
+    }
+
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+    pub fn entities_mut(&mut self) -> &mut Entities {
+        &mut self.entities
+    }
+    pub fn spawn_entity(&mut self, entity: Entity) -> EntityId {
+        self.entities.spawn(entity)
+    }
+    pub fn despawn_entity(&mut self, id: EntityId) -> Option<Entity> {
+        self.entities.despawn(id)
+    }
+    /// Entities whose center is in the given cell.
+    pub fn entities_in_cell(&self, x: u32, y: u32) -> Vec<EntityId> {
+        self.entities.in_cell(x, y, self.block_size)
+    }
+
+    /// Shortest path between two floor cells, see `Map::find_path`.
+    pub fn find_path(
+        &self,
+        from: (u32, u32),
+        to: (u32, u32),
+        neighbours: Neighbours,
+    ) -> Option<Vec<(u32, u32)>> {
+        self.map.find_path(from, to, neighbours)
+    }
+
+    /// Turns a path of cells into as few points as possible to walk along in
+    /// straight lines: the centers of the cells where the path has to turn,
+    /// since from there the next turn can't be seen any more.
+    pub fn smooth_path(&self, path: &[(u32, u32)]) -> Vec<(f32, f32)> {
+        let points = path
+            .iter()
+            .map(|cell| self.cell_center(*cell))
+            .collect::<Vec<(f32, f32)>>();
+        let Some(first) = points.first() else {
+            return Vec::new();
+        };
+
+        let mut smoothed = vec![*first];
+        let mut current = 0;
+        while current + 1 < points.len() {
+            current += 1 + self.furthest_visible(points[current], &points[current + 1..]);
+            smoothed.push(points[current]);
+        }
+        smoothed
+    }
+
     pub fn player_position(&self) -> (f32, f32) {
         self.player.position()
     }
@@ -60,6 +348,11 @@ impl Gamestate {
         self.player.view_direction()
     }
 
+    /// Column and row of the map cell the player stands in.
+    pub fn player_cell(&self) -> (u32, u32) {
+        self.cell(self.player.position())
+    }
+
     pub fn view_distance(&self) -> f32 {
         self.player.view_distance() as f32
     }
@@ -77,6 +370,14 @@ impl Gamestate {
             MoveDirection::Backward => self.player.update_position(delta_time, -1.0),
         }
 
+        if self.noclip {
+            let (x, y) = self.player_position();
+            if !self.inside_map(x, y) {
+                self.player.set_position(x_past, y_past);
+            }
+            return;
+        }
+
         if !self.valdate_position() {
             let (x_curr, y_curr) = self.player_position();
 
@@ -115,7 +416,10 @@ impl Gamestate {
 
             match (new_cords_x, new_cords_y) {
                 (None, None) => {
-                    println!("No solution found, resetting to previous position");
+                    self.report(Diagnostic::CollisionUnresolved {
+                        position: (x_past, y_past),
+                        attempted: (x_curr, y_curr),
+                    });
                     self.player.set_position(x_past, y_past);
                 }
                 (Some(cords), None) | (None, Some(cords)) => {
@@ -132,7 +436,10 @@ impl Gamestate {
                     {
                         self.player.set_position(y_cords.0, y_cords.1);
                     } else {
-                        println!("No solution found, resetting to previous position");
+                        self.report(Diagnostic::CollisionUnresolved {
+                            position: (x_past, y_past),
+                            attempted: (x_curr, y_curr),
+                        });
                         self.player.set_position(x_past, y_past);
                     }
                 }
@@ -140,30 +447,177 @@ impl Gamestate {
         }
     }
 
+    /// Runs one tick: the player's input, unless they are dead, then every
+    /// entity. Everything that changes the game over time should go through
+    /// here, so that recordings replay identically. During a game `use_door`,
+    /// `push` and `fire` are only called from here, for `Action::Use` and
+    /// `Action::Fire`.
+    pub fn apply_input(&mut self, input: &TickInput) {
+        self.weapon.cooldown = self.weapon.cooldown.saturating_sub(input.delta_time);
+        let actions = if self.player_alive() {
+            &input.actions[..]
+        } else {
+            &[]
+        };
+        for action in actions {
+            match action {
+                Action::MoveForward => self.player_move(MoveDirection::Forward, input.delta_time),
+                Action::MoveBackward => self.player_move(MoveDirection::Backward, input.delta_time),
+                Action::TurnLeft => self.player_rotate(TurnDirection::Left, input.delta_time),
+                Action::TurnRight => self.player_rotate(TurnDirection::Right, input.delta_time),
+                Action::Use => {
+                    self.use_door();
+                    self.push();
+                }
+                Action::Fire => {
+                    if let Some(shot) = self.fire() {
+                        self.shots.push(shot);
+                    }
+                }
+            }
+        }
+        self.update_doors(input.delta_time);
+        self.update_push_walls(input.delta_time);
+        self.update_pickups();
+        self.entities.update(input.delta_time);
+        self.update_enemies(input.delta_time);
+        self.update_respawn(input.delta_time);
+    }
+
+    /// Lets every enemy look for the player, take a step towards wherever it
+    /// wants to go and hurt the player if it touches them.
+    fn update_enemies(&mut self, delta_time: u128) {
+        let player = self.player.position();
+        let seconds = delta_time as f32 / 1_000_000_000.0;
+        let enemies = self
+            .entities
+            .iter()
+            .filter(|(_, entity)| matches!(entity.behaviour, Behaviour::Enemy(_)))
+            .map(|(id, _)| id)
+            .collect::<Vec<EntityId>>();
+
+        for id in enemies {
+            let Some(entity) = self.entities.get(id) else {
+                continue;
+            };
+            let Behaviour::Enemy(enemy) = &entity.behaviour else {
+                continue;
+            };
+            let position = entity.position;
+            let sees_player = self.player_alive()
+                && enemy.in_view(position, entity.facing, player)
+                && self.has_line_of_sight(position, player);
+            let (speed, damage_per_second) = (enemy.speed, enemy.damage_per_second);
+
+            let Some(Entity {
+                behaviour: Behaviour::Enemy(enemy),
+                ..
+            }) = self.entities.get_mut(id)
+            else {
+                continue;
+            };
+            let target = enemy.think(position, sees_player.then_some(player), delta_time);
+
+            if let Some(step) = target.and_then(|target| self.next_step(position, target)) {
+                let (dx, dy) = (step.0 - position.0, step.1 - position.1);
+                let remaining = ai::distance(position, step);
+                let travel = speed * seconds;
+                if let Some(entity) = self.entities.get_mut(id) {
+                    if remaining <= travel {
+                        entity.position = step;
+                    } else {
+                        entity.position.0 += dx / remaining * travel;
+                        entity.position.1 += dy / remaining * travel;
+                    }
+                    if remaining > 0.0 {
+                        entity.facing = ai::facing_towards(dx, dy);
+                    }
+                }
+            }
+
+            let touches_player = self
+                .entities
+                .get(id)
+                .is_some_and(|entity| ai::distance(entity.position, player) <= entity.radius);
+            if touches_player {
+                self.damage_player(damage_per_second * seconds, DamageSource::Enemy(id));
+            }
+        }
+    }
+
+    /// The point to walk straight towards to get from `from` to `to` without
+    /// touching a wall: the furthest cell center along the shortest path
+    /// that can be seen from `from`, or `to` itself once that can be seen.
+    /// `None` if `to` can't be reached.
+    fn next_step(&self, from: (f32, f32), to: (f32, f32)) -> Option<(f32, f32)> {
+        if !self.inside_map(from.0, from.1) || !self.inside_map(to.0, to.1) {
+            return None;
+        }
+        let path = self.find_path(self.cell(from), self.cell(to), Neighbours::Eight)?;
+        let mut points = path[1..]
+            .iter()
+            .map(|cell| self.cell_center(*cell))
+            .collect::<Vec<(f32, f32)>>();
+        if let Some(last) = points.last_mut() {
+            *last = to;
+        } else {
+            return Some(to);
+        }
+        Some(points[self.furthest_visible(from, &points)])
+    }
+
+    /// Follows a single ray from any point, `angle` being in degrees with 0
+    /// along the x axis and 90 along the y axis. Returns where the ray stopped
+    /// and whether that was because it is about to enter a wall rather than
+    /// having gone `max_distance`. A ray starting outside of the map or in a
+    /// cell that blocks, see `Map::blocks`, stops right away.
+    pub fn cast_ray(&self, origin: (f32, f32), angle: f32, max_distance: f32) -> (f32, f32, bool) {
+        if !self.inside_map(origin.0, origin.1)
+            || self.map.blocks(self.block_id(origin.0, origin.1))
+        {
+            return (origin.0, origin.1, true);
+        }
+        self.ray_wall_collision(origin, angle.to_radians(), max_distance)
+    }
+
+    /// Whether nothing but floor lies between two points.
+    pub fn has_line_of_sight(&self, a: (f32, f32), b: (f32, f32)) -> bool {
+        let distance = ai::distance(a, b);
+        let angle = (b.1 - a.1).atan2(b.0 - a.0).to_degrees();
+        let (_, _, hit) = self.cast_ray(a, angle, distance);
+        !hit
+    }
+
     pub fn cast_rays(&self) -> Vec<(f32, f32, bool)> {
         const RADIAN_MULTIPLIER: f32 = std::f32::consts::PI / 180.0;
         let player_angle = self.player.view_direction() * RADIAN_MULTIPLIER;
+        let view_distance = self.player.view_distance() as f32;
 
         self.ray_angles
             .iter()
             .map(|ray_angle| {
                 let angle = player_angle - (ray_angle * RADIAN_MULTIPLIER);
 
-                self.ray_wall_collision(angle)
+                self.ray_wall_collision(self.player.position(), angle, view_distance)
             })
             .collect::<Vec<(f32, f32, bool)>>()
     }
 
-    fn ray_wall_collision(&self, ray_angle: f32) -> (f32, f32, bool) {
+    /// Follows a ray from `origin` until it is about to enter a wall or has
+    /// gone `view_distance`. Returns where it stopped and whether it hit a wall.
+    fn ray_wall_collision(
+        &self,
+        (origin_x, origin_y): (f32, f32),
+        ray_angle: f32,
+        view_distance: f32,
+    ) -> (f32, f32, bool) {
         const RADIAN_MULTIPLIER: f32 = std::f32::consts::PI / 180.0;
-        let view_distance = self.player.view_distance() as f32;
-        let (player_x, player_y) = self.player.position();
         let (sin, cos) = ray_angle.sin_cos();
 
-        let end_x = cos * view_distance + player_x;
-        let end_y = sin * view_distance + player_y;
+        let end_x = cos * view_distance + origin_x;
+        let end_y = sin * view_distance + origin_y;
 
-        let atan = (end_y - player_y).atan2(end_x - player_x) / RADIAN_MULTIPLIER + 180.0;
+        let atan = (end_y - origin_y).atan2(end_x - origin_x) / RADIAN_MULTIPLIER + 180.0;
         let quartet = (atan / (360.0 / 4.0)) as i32;
 
         enum CordDir {
@@ -189,39 +643,48 @@ impl Gamestate {
         }
 
         let mut dist = 0.0;
-        // (cos * dist + player_x, sin * dist + player_y)
+        // (cos * dist + origin_x, sin * dist + origin_y)
         while dist < view_distance {
-            let x_next = cos * (dist + 10.0) + player_x;
-            let y_next = sin * (dist + 10.0) + player_y;
+            let x_next = cos * (dist + 10.0) + origin_x;
+            let y_next = sin * (dist + 10.0) + origin_y;
             if self.map_walls().contains(&self.block_id(x_next, y_next)) {
-                return (cos * dist + player_x, sin * dist + player_y, true);
+                return (cos * dist + origin_x, sin * dist + origin_y, true);
+            }
+            let cell = self.block_id(x_next, y_next);
+            let hit = self
+                .door_hit(cell, (origin_x, origin_y), (cos, sin))
+                .or_else(|| self.push_wall_hit(cell, (origin_x, origin_y), (cos, sin)));
+            if let Some(hit_dist) = hit {
+                if hit_dist <= view_distance {
+                    return (cos * hit_dist + origin_x, sin * hit_dist + origin_y, true);
+                }
             }
 
             let (neg_x, pos_y, pos_x, neg_y) = self.block_corners(self.block_id(x_next, y_next));
             let new_dist = match (&hor, &vert) {
                 (CordDir::Negative, CordDir::Positive) => {
-                    smaller_distance((cos, sin), (neg_x, pos_y), (player_x, player_y))
+                    smaller_distance((cos, sin), (neg_x, pos_y), (origin_x, origin_y))
                 }
                 (CordDir::Positive, CordDir::Positive) => {
-                    smaller_distance((cos, sin), (pos_x, pos_y), (player_x, player_y))
+                    smaller_distance((cos, sin), (pos_x, pos_y), (origin_x, origin_y))
                 }
                 (CordDir::Positive, CordDir::Negative) => {
-                    smaller_distance((cos, sin), (neg_x, neg_y), (player_x, player_y))
+                    smaller_distance((cos, sin), (neg_x, neg_y), (origin_x, origin_y))
                 }
                 (CordDir::Negative, CordDir::Negative) => {
-                    smaller_distance((cos, sin), (pos_x, neg_y), (player_x, player_y))
+                    smaller_distance((cos, sin), (pos_x, neg_y), (origin_x, origin_y))
                 }
             };
 
             if new_dist == dist {
-                return (cos * dist + player_x, sin * dist + player_y, true);
+                return (cos * dist + origin_x, sin * dist + origin_y, true);
             }
             dist = new_dist;
         }
 
         (
-            cos * view_distance + player_x,
-            sin * view_distance + player_y,
+            cos * view_distance + origin_x,
+            sin * view_distance + origin_y,
             false,
         )
     }
@@ -234,7 +697,32 @@ impl Gamestate {
             return false;
         }
 
-        !self.map.walls().contains(&self.block_id(x, y))
+        !self.map.blocks(self.block_id(x, y))
+    }
+
+    /// Index of the last of `points` that can be seen from `from`, stopping
+    /// at the first one that can't. `0` if not even the first can be seen.
+    fn furthest_visible(&self, from: (f32, f32), points: &[(f32, f32)]) -> usize {
+        points
+            .iter()
+            .take_while(|point| self.has_line_of_sight(from, **point))
+            .count()
+            .saturating_sub(1)
+    }
+
+    fn cell(&self, (x, y): (f32, f32)) -> (u32, u32) {
+        let block_size = self.block_size as f32;
+        ((x / block_size) as u32, (y / block_size) as u32)
+    }
+
+    fn cell_center(&self, (x, y): (u32, u32)) -> (f32, f32) {
+        let block_size = self.block_size as f32;
+        ((x as f32 + 0.5) * block_size, (y as f32 + 0.5) * block_size)
+    }
+
+    fn inside_map(&self, x: f32, y: f32) -> bool {
+        let (w, h) = (self.map.width() as f32, self.map.height() as f32);
+        x >= 0.0 && y >= 0.0 && x < w * self.block_size as f32 && y < h * self.block_size as f32
     }
 
     fn block_id(&self, x: f32, y: f32) -> u16 {
@@ -261,6 +749,22 @@ impl Gamestate {
     }
 }
 
+#[cfg(test)]
+impl Gamestate {
+    /// A game on a map given as text, see `Map::parse`, with 64 unit cells.
+    pub(crate) fn from_map(text: &str) -> Gamestate {
+        let mut gamestate = Gamestate::new(vec![vec![false]], 0.0, 0.0, 64, 16);
+        gamestate.set_map(text).unwrap();
+        gamestate
+    }
+}
+
+/// A file in the temp directory for a test, unique to this process.
+#[cfg(test)]
+pub(crate) fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}-{name}", std::process::id()))
+}
+
 pub enum TurnDirection {
     Left,
     Right,
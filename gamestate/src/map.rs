@@ -1,8 +1,53 @@
+use std::collections::BTreeMap;
+
+use crate::ai::EnemyAi;
+use crate::door::Door;
+use crate::entity::{Behaviour, Entity};
+use crate::item::Item;
+use crate::push_wall::{PushWall, PushWallState};
+
+/// An entity a map file places on a floor cell, see `Map::parse`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Marker {
+    Enemy,
+    Pickup(Item),
+}
+
+/// Markers by the column and row of their cell, in reading order.
+pub type Markers = Vec<((u8, u8), Marker)>;
+
+impl Marker {
+    const ENEMY_HEALTH: f32 = 50.0;
+
+    /// The entity the marker stands for, at `position` in world units.
+    pub fn entity(self, position: (f32, f32)) -> Entity {
+        let mut entity = Entity::new(position, 0);
+        match self {
+            Marker::Enemy => {
+                entity.health = Some(Marker::ENEMY_HEALTH);
+                entity.behaviour = Behaviour::Enemy(EnemyAi::new(vec![position]));
+            }
+            Marker::Pickup(item) => entity.behaviour = Behaviour::Pickup(item),
+        }
+        entity
+    }
+}
+
 #[allow(dead_code)]
 pub struct Map {
     width: u8,
     height: u8,
     walls: Vec<u16>,
+    /// One entry per cell, `true` once the player has seen it.
+    explored: Vec<bool>,
+    /// What each wall is made of, one entry per cell. Plain walls and floor
+    /// are `0`.
+    materials: Vec<u8>,
+    /// Door cells by id. Doors are not in `walls`.
+    doors: BTreeMap<u16, Door>,
+    /// Push-walls by the id of the cell they are in. They are not in `walls`
+    /// either, since they move.
+    push_walls: BTreeMap<u16, PushWall>,
 }
 
 impl Map {
@@ -22,16 +67,305 @@ impl Map {
             width,
             height,
             walls,
+            explored: vec![false; width as usize * height as usize],
+            materials: vec![0; width as usize * height as usize],
+            doors: BTreeMap::new(),
+            push_walls: BTreeMap::new(),
         }
     }
 
+    /// Parses a map from text, one line per row: `#` is a wall, `1` to `9` are
+    /// walls made of that material, `.` is floor and `P` is the floor cell the
+    /// player starts on. `D` is a door and `a` to `z` are doors locked with
+    /// key 1 to 26, and `S` is a secret push-wall.
+    ///
+    /// `E` is an enemy guarding its cell, `H` health, `A` ammo and `T`
+    /// treasure worth points, all on floor. Every `K` is a key, the first one
+    /// key 1, the second key 2 and so on.
+    ///
+    /// Returns the map, the start cell as column and row if there is one, and
+    /// the markers.
+    pub fn parse(text: &str) -> Result<(Map, Option<(u8, u8)>, Markers), String> {
+        let rows = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<&str>>();
+
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if width == 0 {
+            return Err("The map is empty".to_string());
+        }
+        if width > u8::MAX as usize || rows.len() > u8::MAX as usize {
+            return Err(format!(
+                "The map is {width}x{}, at most {}x{} is supported",
+                rows.len(),
+                u8::MAX,
+                u8::MAX
+            ));
+        }
+
+        let mut start = None;
+        let mut matrix = Vec::new();
+        let mut materials = Vec::new();
+        let mut doors = Vec::new();
+        let mut push_walls = BTreeMap::new();
+        let mut markers = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(format!(
+                    "Row {} of the map is {} cells wide, expected {width}",
+                    y + 1,
+                    row.chars().count()
+                ));
+            }
+            let mut cells = Vec::new();
+            for (x, cell) in row.chars().enumerate() {
+                materials.push(cell.to_digit(10).unwrap_or(0) as u8);
+                match cell {
+                    '#' | '1'..='9' => cells.push(true),
+                    '.' => cells.push(false),
+                    'D' | 'a'..='z' => {
+                        let key = cell.is_ascii_lowercase().then(|| cell as u8 - b'a' + 1);
+                        doors.push(((x, y), key));
+                        cells.push(false);
+                    }
+                    'S' => {
+                        push_walls.insert((y * width + x) as u16, PushWall::new());
+                        cells.push(false);
+                    }
+                    'P' => {
+                        start = Some((x as u8, y as u8));
+                        cells.push(false);
+                    }
+                    'E' | 'H' | 'A' | 'T' | 'K' => {
+                        let marker = match cell {
+                            'E' => Marker::Enemy,
+                            'H' => Marker::Pickup(Item::Health(25.0)),
+                            'A' => Marker::Pickup(Item::Ammo(10)),
+                            'T' => Marker::Pickup(Item::Score(100)),
+                            _ => {
+                                let keys = markers
+                                    .iter()
+                                    .filter(|(_, marker)| {
+                                        matches!(marker, Marker::Pickup(Item::Key(_)))
+                                    })
+                                    .count();
+                                Marker::Pickup(Item::Key(keys as u8 + 1))
+                            }
+                        };
+                        markers.push(((x as u8, y as u8), marker));
+                        cells.push(false);
+                    }
+                    _ => return Err(format!("Unknown cell '{cell}' in row {} of the map", y + 1)),
+                }
+            }
+            matrix.push(cells);
+        }
+
+        let is_wall = |x: usize, y: usize| {
+            matrix[y].get(x).copied().unwrap_or(true)
+                || push_walls.contains_key(&((y * width + x) as u16))
+        };
+        let doors = doors
+            .into_iter()
+            .map(|((x, y), key)| {
+                // Doors between walls on the left and right are crossed
+                // along y, so their slab runs along x.
+                let along_x = x > 0 && is_wall(x - 1, y) && is_wall(x + 1, y);
+                ((y * width + x) as u16, Door::new(key, along_x))
+            })
+            .collect::<BTreeMap<u16, Door>>();
+
+        let mut map = Map::new(matrix);
+        for cell in doors.keys() {
+            materials[*cell as usize] = Door::MATERIAL;
+        }
+        map.materials = materials;
+        map.doors = doors;
+        map.push_walls = push_walls;
+        Ok((map, start, markers))
+    }
+
     pub fn walls(&self) -> &Vec<u16> {
         &self.walls
     }
+
+    pub fn door(&self, block_id: u16) -> Option<&Door> {
+        self.doors.get(&block_id)
+    }
+    pub fn door_mut(&mut self, block_id: u16) -> Option<&mut Door> {
+        self.doors.get_mut(&block_id)
+    }
+    pub fn door_cells(&self) -> Vec<u16> {
+        self.doors.keys().copied().collect()
+    }
+
+    pub fn push_wall(&self, block_id: u16) -> Option<&PushWall> {
+        self.push_walls.get(&block_id)
+    }
+    pub(crate) fn push_wall_mut(&mut self, block_id: u16) -> Option<&mut PushWall> {
+        self.push_walls.get_mut(&block_id)
+    }
+    pub fn push_wall_cells(&self) -> impl Iterator<Item = u16> + '_ {
+        self.push_walls.keys().copied()
+    }
+    /// The cell a push-wall is moving into, if it is moving.
+    pub fn push_wall_target(&self, block_id: u16) -> Option<u16> {
+        match self.push_wall(block_id)?.state() {
+            PushWallState::Moving { direction, .. } => self.neighbour(block_id, direction),
+            _ => None,
+        }
+    }
+    pub(crate) fn move_push_wall(&mut self, from: u16, to: u16) {
+        if let Some(wall) = self.push_walls.remove(&from) {
+            self.push_walls.insert(to, wall);
+        }
+    }
+
+    /// The cell next to `block_id` in `direction`, if it is on the map.
+    pub fn neighbour(&self, block_id: u16, (dx, dy): (i8, i8)) -> Option<u16> {
+        let width = self.width as i32;
+        let (x, y) = (
+            (block_id as i32 % width) + dx as i32,
+            (block_id as i32 / width) + dy as i32,
+        );
+        (x >= 0 && y >= 0 && x < width && y < self.height as i32).then(|| (y * width + x) as u16)
+    }
+
+    /// Whether nothing can pass through a cell: walls, doors that are not
+    /// fully open and push-walls, which block both cells while moving.
+    pub fn blocks(&self, block_id: u16) -> bool {
+        self.walls.contains(&block_id)
+            || self.door(block_id).is_some_and(Door::blocks)
+            || self.push_walls.contains_key(&block_id)
+            || self
+                .push_wall_cells()
+                .any(|wall| self.push_wall_target(wall) == Some(block_id))
+    }
+
+    /// `blocks` for every cell at once, indexed by cell id.
+    pub fn blocked_cells(&self) -> Vec<bool> {
+        let mut blocked = vec![false; self.width as usize * self.height as usize];
+        let doors = self
+            .doors
+            .iter()
+            .filter(|(_, door)| door.blocks())
+            .map(|(cell, _)| *cell);
+        let push_walls = self
+            .push_wall_cells()
+            .flat_map(|cell| [Some(cell), self.push_wall_target(cell)])
+            .flatten();
+        for cell in self.walls.iter().copied().chain(doors).chain(push_walls) {
+            if let Some(blocked) = blocked.get_mut(cell as usize) {
+                *blocked = true;
+            }
+        }
+        blocked
+    }
+
     pub fn width(&self) -> u8 {
         self.width
     }
     pub fn height(&self) -> u8 {
         self.height
     }
+
+    pub fn materials(&self) -> &Vec<u8> {
+        &self.materials
+    }
+
+    pub fn explored(&self) -> &Vec<bool> {
+        &self.explored
+    }
+    pub fn explore(&mut self, block_id: u16) {
+        if let Some(cell) = self.explored.get_mut(block_id as usize) {
+            *cell = true;
+        }
+    }
+    pub fn set_explored(&mut self, explored: Vec<bool>) -> Result<(), String> {
+        if explored.len() != self.explored.len() {
+            return Err(format!(
+                "Explored cells don't match the map: expected {}, got {}",
+                self.explored.len(),
+                explored.len()
+            ));
+        }
+        self.explored = explored;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_are_floor_and_keys_count_up() {
+        let (map, start, markers) = Map::parse(
+            "\
+#######
+#PK.EA#
+#.H.TK#
+#######",
+        )
+        .unwrap();
+
+        assert_eq!(start, Some((1, 1)));
+        assert!(markers
+            .iter()
+            .all(|((x, y), _)| !map.blocks(*y as u16 * 7 + *x as u16)));
+        assert_eq!(
+            markers,
+            vec![
+                ((2, 1), Marker::Pickup(Item::Key(1))),
+                ((4, 1), Marker::Enemy),
+                ((5, 1), Marker::Pickup(Item::Ammo(10))),
+                ((2, 2), Marker::Pickup(Item::Health(25.0))),
+                ((4, 2), Marker::Pickup(Item::Score(100))),
+                ((5, 2), Marker::Pickup(Item::Key(2))),
+            ]
+        );
+    }
+
+    #[test]
+    fn loading_a_map_spawns_its_markers() {
+        let gamestate = crate::Gamestate::from_map(
+            "\
+#####
+#PET#
+#####",
+        );
+
+        let mut entities = gamestate.entities().iter().map(|(_, entity)| entity);
+        let enemy = entities.next().unwrap();
+        assert_eq!(enemy.position, (160.0, 96.0));
+        assert_eq!(enemy.health, Some(Marker::ENEMY_HEALTH));
+        assert!(matches!(enemy.behaviour, Behaviour::Enemy(_)));
+        let treasure = entities.next().unwrap();
+        assert_eq!(treasure.position, (224.0, 96.0));
+        assert_eq!(treasure.behaviour, Behaviour::Pickup(Item::Score(100)));
+        assert!(entities.next().is_none());
+    }
+
+    #[test]
+    fn rays_starting_in_doors_or_push_walls_stop_right_away() {
+        let gamestate = crate::Gamestate::from_map(
+            "\
+######
+#PDS.#
+######",
+        );
+
+        for origin in [(140.0, 96.0), (200.0, 96.0)] {
+            assert_eq!(
+                gamestate.cast_ray(origin, 180.0, 100.0),
+                (origin.0, origin.1, true)
+            );
+        }
+        assert_eq!(
+            gamestate.cast_ray((288.0, 96.0), 0.0, 10.0),
+            (298.0, 96.0, false)
+        );
+    }
 }
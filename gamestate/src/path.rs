@@ -0,0 +1,201 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::map::Map;
+
+/// Which cells count as next to each other when finding a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighbours {
+    /// Up, down, left and right.
+    Four,
+    /// Also the diagonals, as long as the move doesn't cut the corner of a
+    /// wall.
+    Eight,
+}
+
+impl Map {
+    /// Cost of a straight step, a diagonal one costs `DIAGONAL_COST`. Roughly
+    /// 1 and the square root of 2, scaled so that costs stay integers.
+    const STRAIGHT_COST: u32 = 10;
+    const DIAGONAL_COST: u32 = 14;
+
+    /// Finds a shortest path between two floor cells with A*, going around
+    /// doors that are not fully open. Returns the cells from `from` to `to`,
+    /// both included, or `None` if either is blocked, outside of the map or
+    /// `to` can't be reached.
+    pub fn find_path(
+        &self,
+        from: (u32, u32),
+        to: (u32, u32),
+        neighbours: Neighbours,
+    ) -> Option<Vec<(u32, u32)>> {
+        let (width, height) = (self.width() as u32, self.height() as u32);
+        let walls = self.blocked_cells();
+        let index = |(x, y): (u32, u32)| (y * width + x) as usize;
+        let floor = |(x, y): (i64, i64)| {
+            x >= 0
+                && y >= 0
+                && x < width as i64
+                && y < height as i64
+                && !walls[index((x as u32, y as u32))]
+        };
+        if !floor((from.0 as i64, from.1 as i64)) || !floor((to.0 as i64, to.1 as i64)) {
+            return None;
+        }
+
+        let estimate = |(x, y): (u32, u32)| {
+            let (dx, dy) = (x.abs_diff(to.0), y.abs_diff(to.1));
+            match neighbours {
+                Neighbours::Four => (dx + dy) * Map::STRAIGHT_COST,
+                Neighbours::Eight => {
+                    dx.max(dy) * Map::STRAIGHT_COST
+                        + dx.min(dy) * (Map::DIAGONAL_COST - Map::STRAIGHT_COST)
+                }
+            }
+        };
+
+        let mut cost = vec![u32::MAX; walls.len()];
+        let mut came_from = vec![None; walls.len()];
+        // Ties are broken by cell index, so the same path is found every time.
+        let mut open = BinaryHeap::from([Reverse((estimate(from), index(from), from))]);
+        cost[index(from)] = 0;
+        while let Some(Reverse((_, _, cell))) = open.pop() {
+            if cell == to {
+                let mut path = vec![to];
+                let mut current = to;
+                while let Some(previous) = came_from[index(current)] {
+                    path.push(previous);
+                    current = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let (x, y) = (cell.0 as i64, cell.1 as i64);
+            for (dx, dy) in [
+                (-1, 0),
+                (1, 0),
+                (0, -1),
+                (0, 1),
+                (-1, -1),
+                (1, -1),
+                (-1, 1),
+                (1, 1),
+            ] {
+                let diagonal = dx != 0 && dy != 0;
+                if diagonal && neighbours == Neighbours::Four {
+                    continue;
+                }
+                if !floor((x + dx, y + dy))
+                    || diagonal && (!floor((x + dx, y)) || !floor((x, y + dy)))
+                {
+                    continue;
+                }
+
+                let next = ((x + dx) as u32, (y + dy) as u32);
+                let step = if diagonal {
+                    Map::DIAGONAL_COST
+                } else {
+                    Map::STRAIGHT_COST
+                };
+                let next_cost = cost[index(cell)] + step;
+                if next_cost < cost[index(next)] {
+                    cost[index(next)] = next_cost;
+                    came_from[index(next)] = Some(cell);
+                    open.push(Reverse((next_cost + estimate(next), index(next), next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(text: &str) -> Map {
+        Map::parse(text).unwrap().0
+    }
+
+    #[test]
+    fn straight_path() {
+        let map = map("\
+######
+#....#
+######");
+        assert_eq!(
+            map.find_path((1, 1), (4, 1), Neighbours::Four),
+            Some(vec![(1, 1), (2, 1), (3, 1), (4, 1)])
+        );
+        assert_eq!(
+            map.find_path((1, 1), (1, 1), Neighbours::Eight),
+            Some(vec![(1, 1)])
+        );
+    }
+
+    #[test]
+    fn detour_around_walls() {
+        let map = map("\
+#####
+#...#
+#.#.#
+#...#
+#####");
+        let path = map.find_path((1, 2), (3, 2), Neighbours::Four).unwrap();
+        assert_eq!(path.len(), 5);
+        assert_eq!((path[0], path[4]), ((1, 2), (3, 2)));
+        assert!(!path.contains(&(2, 2)));
+        for pair in path.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1);
+        }
+    }
+
+    #[test]
+    fn unreachable_or_blocked() {
+        let map = map("\
+#####
+#.#.#
+#####");
+        assert_eq!(map.find_path((1, 1), (3, 1), Neighbours::Eight), None);
+        assert_eq!(map.find_path((1, 1), (2, 1), Neighbours::Eight), None);
+        assert_eq!(map.find_path((1, 1), (9, 9), Neighbours::Eight), None);
+
+        let map = self::map(
+            "\
+#####
+#.D.#
+#####",
+        );
+        assert_eq!(map.find_path((1, 1), (3, 1), Neighbours::Four), None);
+    }
+
+    #[test]
+    fn diagonals_only_with_eight_neighbours_and_never_past_corners() {
+        let open = map("\
+####
+#..#
+#..#
+####");
+        assert_eq!(
+            open.find_path((1, 1), (2, 2), Neighbours::Eight),
+            Some(vec![(1, 1), (2, 2)])
+        );
+        assert_eq!(
+            open.find_path((1, 1), (2, 2), Neighbours::Four)
+                .map(|path| path.len()),
+            Some(3)
+        );
+
+        let corner = map("\
+####
+#.##
+#..#
+####");
+        assert_eq!(
+            corner.find_path((1, 1), (2, 2), Neighbours::Eight),
+            Some(vec![(1, 1), (1, 2), (2, 2)])
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use crate::item::Inventory;
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct Player {
@@ -6,6 +8,9 @@ pub struct Player {
     field_of_view: f32,
     posisiton: (f32, f32),
     view_distance: u16,
+    health: f32,
+    armor: f32,
+    inventory: Inventory,
 }
 
 impl Player {
@@ -16,9 +21,33 @@ impl Player {
             field_of_view: fov,
             posisiton: (x_position, y_position),
             view_distance,
+            health: Player::MAX_HEALTH,
+            armor: 0.0,
+            inventory: Inventory::new(),
         }
     }
 
+    pub const MAX_HEALTH: f32 = 100.0;
+
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+    pub fn set_health(&mut self, health: f32) {
+        self.health = health;
+    }
+    pub fn armor(&self) -> f32 {
+        self.armor
+    }
+    pub fn set_armor(&mut self, armor: f32) {
+        self.armor = armor;
+    }
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+    pub fn inventory_mut(&mut self) -> &mut Inventory {
+        &mut self.inventory
+    }
+
     pub fn position(&self) -> (f32, f32) {
         self.posisiton
     }
@@ -38,9 +67,19 @@ impl Player {
         )
     }
 
+    /// The direction the player faces and moves forward in, in degrees. It
+    /// is opposite to the view direction, which `update_position` moves
+    /// against.
+    pub fn facing(&self) -> f32 {
+        self.view_direction + 180.0
+    }
+
     pub fn view_direction(&self) -> f32 {
         self.view_direction
     }
+    pub fn set_view_direction(&mut self, view_direction: f32) {
+        self.view_direction = view_direction;
+    }
     pub fn rotate(&mut self, rotation_val: f32, delta_time: u128) {
         self.view_direction += rotation_val * (delta_time as f32) * 0.0000004;
         if self.view_direction < 0.0 {
@@ -52,6 +91,12 @@ impl Player {
     pub fn set_position(&mut self, x: f32, y: f32) {
         self.posisiton = (x, y);
     }
+    pub fn field_of_view(&self) -> f32 {
+        self.field_of_view
+    }
+    pub fn set_field_of_view(&mut self, fov: f32) {
+        self.field_of_view = fov;
+    }
     pub fn view_distance(&self) -> u16 {
         self.view_distance
     }
@@ -0,0 +1,284 @@
+use crate::Gamestate;
+
+/// What a push-wall is doing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PushWallState {
+    /// Waiting to be pushed.
+    Idle,
+    /// Sliding from its cell into the next one along `direction`, `offset` of
+    /// the way there. `cells_left` is how many more cells it moves after that.
+    Moving {
+        direction: (i8, i8),
+        offset: f32,
+        cells_left: u8,
+    },
+    /// Done moving for good.
+    Stopped,
+}
+
+/// A secret wall that looks and blocks like any other wall, but slides away
+/// when the player pushes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushWall {
+    state: PushWallState,
+}
+
+impl PushWall {
+    /// How many cells a push moves the wall, unless something is in the way.
+    pub const DISTANCE: u8 = 2;
+    /// How long moving by one cell takes, in nanoseconds.
+    pub const CELL_TIME: u128 = 1_000_000_000;
+
+    pub(crate) fn new() -> PushWall {
+        PushWall {
+            state: PushWallState::Idle,
+        }
+    }
+
+    pub fn state(&self) -> PushWallState {
+        self.state
+    }
+
+    /// Puts the wall in a saved state.
+    pub(crate) fn restore(&mut self, state: PushWallState) {
+        self.state = state;
+    }
+}
+
+impl Gamestate {
+    /// The push-wall in a cell, if there is one. A moving wall is in the cell
+    /// it is leaving until it has fully entered the next one.
+    pub fn push_wall(&self, x: u32, y: u32) -> Option<&PushWall> {
+        if x >= self.map_width() as u32 || y >= self.map_height() as u32 {
+            return None;
+        }
+        self.map.push_wall((y * self.map_width() as u32 + x) as u16)
+    }
+
+    /// Ids of the cells push-walls are in, `y * width + x`.
+    pub fn push_wall_cells(&self) -> Vec<u16> {
+        self.map.push_wall_cells().collect()
+    }
+
+    /// Pushes the push-wall right in front of the player away from them, along
+    /// whichever axis they face the most. Walls that were pushed before or
+    /// have something behind them don't move.
+    pub fn push(&mut self) {
+        let Some(cell) = self.cell_in_front() else {
+            return;
+        };
+        if self.map.push_wall(cell).map(PushWall::state) != Some(PushWallState::Idle) {
+            return;
+        }
+
+        let (sin, cos) = self.player.facing().to_radians().sin_cos();
+        let direction = if cos.abs() >= sin.abs() {
+            (cos.signum() as i8, 0)
+        } else {
+            (0, sin.signum() as i8)
+        };
+        match self.map.neighbour(cell, direction) {
+            Some(next) if self.can_push_into(next) => {}
+            _ => return,
+        }
+        self.set_push_wall_state(
+            cell,
+            PushWallState::Moving {
+                direction,
+                offset: 0.0,
+                cells_left: PushWall::DISTANCE - 1,
+            },
+        );
+    }
+
+    /// Moves every push-wall that is moving. A wall that has entered a cell
+    /// goes on into the next one only if that is free.
+    pub(crate) fn update_push_walls(&mut self, delta_time: u128) {
+        let step = delta_time as f32 / PushWall::CELL_TIME as f32;
+        for cell in self.map.push_wall_cells().collect::<Vec<u16>>() {
+            let Some(PushWallState::Moving {
+                direction,
+                offset,
+                cells_left,
+            }) = self.map.push_wall(cell).map(PushWall::state)
+            else {
+                continue;
+            };
+            let offset = offset + step;
+            let next = match self.map.neighbour(cell, direction) {
+                Some(next) if offset >= 1.0 => next,
+                _ => {
+                    self.set_push_wall_state(
+                        cell,
+                        PushWallState::Moving {
+                            direction,
+                            offset,
+                            cells_left,
+                        },
+                    );
+                    continue;
+                }
+            };
+
+            // Stopped first, so that the wall doesn't block the cell it
+            // checks next.
+            self.map.move_push_wall(cell, next);
+            self.set_push_wall_state(next, PushWallState::Stopped);
+            match self.map.neighbour(next, direction) {
+                Some(after) if cells_left > 0 && self.can_push_into(after) => {
+                    self.set_push_wall_state(
+                        next,
+                        PushWallState::Moving {
+                            direction,
+                            offset: offset - 1.0,
+                            cells_left: cells_left - 1,
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Where a ray from `origin` along `(cos, sin)` meets a push-wall that is
+    /// in `cell` or moving into it, as the distance from `origin`.
+    pub(crate) fn push_wall_hit(
+        &self,
+        cell: u16,
+        origin: (f32, f32),
+        (cos, sin): (f32, f32),
+    ) -> Option<f32> {
+        let block_size = self.block_size as f32;
+        self.map
+            .push_wall_cells()
+            .filter(|wall| *wall == cell || self.map.push_wall_target(*wall) == Some(cell))
+            .filter_map(|wall| {
+                let (mut left, mut top, _, _) = self.block_corners(wall);
+                if let Some(PushWallState::Moving {
+                    direction, offset, ..
+                }) = self.map.push_wall(wall).map(PushWall::state)
+                {
+                    left += direction.0 as f32 * offset * block_size;
+                    top += direction.1 as f32 * offset * block_size;
+                }
+
+                // Where the ray enters and leaves the wall's square.
+                let (mut near, mut far) = (f32::NEG_INFINITY, f32::INFINITY);
+                for (start, origin, direction) in [(left, origin.0, cos), (top, origin.1, sin)] {
+                    if direction == 0.0 {
+                        if origin < start || origin > start + block_size {
+                            return None;
+                        }
+                        continue;
+                    }
+                    let (a, b) = (
+                        (start - origin) / direction,
+                        (start + block_size - origin) / direction,
+                    );
+                    near = near.max(a.min(b));
+                    far = far.min(a.max(b));
+                }
+                (near <= far && far >= 0.0).then_some(near.max(0.0))
+            })
+            .min_by(f32::total_cmp)
+    }
+
+    /// Whether a push-wall can move into a cell: floor that nothing stands in.
+    fn can_push_into(&self, cell: u16) -> bool {
+        !self.map.blocks(cell) && self.map.door(cell).is_none() && !self.occupied(cell)
+    }
+
+    fn set_push_wall_state(&mut self, cell: u16, state: PushWallState) {
+        if let Some(wall) = self.map.push_wall_mut(cell) {
+            wall.state = state;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Entity;
+
+    /// A corridor with the player at (1, 1) looking east at the push-wall
+    /// next to them, with `rest` after it.
+    fn corridor(rest: &str) -> Gamestate {
+        let mut gamestate = Gamestate::from_map(&format!(
+            "\
+{walls}
+#PS{rest}
+{walls}",
+            walls = "#".repeat(rest.len() + 3)
+        ));
+        gamestate.player.set_view_direction(180.0);
+        gamestate
+    }
+
+    /// Pushes the wall and lets it move for as long as it could.
+    fn push_all_the_way(gamestate: &mut Gamestate) {
+        gamestate.push();
+        for _ in 0..=PushWall::DISTANCE {
+            gamestate.update_push_walls(PushWall::CELL_TIME);
+        }
+    }
+
+    #[test]
+    fn slides_two_cells() {
+        let mut gamestate = corridor("....#");
+        push_all_the_way(&mut gamestate);
+        assert_eq!(gamestate.push_wall_cells(), vec![12]);
+        assert_eq!(
+            gamestate.push_wall(4, 1).map(PushWall::state),
+            Some(PushWallState::Stopped)
+        );
+
+        // It only moves once.
+        gamestate.teleport(3, 1).unwrap();
+        push_all_the_way(&mut gamestate);
+        assert_eq!(gamestate.push_wall_cells(), vec![12]);
+    }
+
+    #[test]
+    fn stops_early_at_walls_doors_and_entities() {
+        for rest in [".#", ".D.#", "...#"] {
+            let mut gamestate = corridor(rest);
+            if rest == "...#" {
+                gamestate.spawn_entity(Entity::new((288.0, 96.0), 0));
+            }
+            push_all_the_way(&mut gamestate);
+            assert_eq!(
+                gamestate.push_wall(3, 1).map(PushWall::state),
+                Some(PushWallState::Stopped),
+                "{rest}"
+            );
+        }
+
+        let mut gamestate = corridor("#");
+        push_all_the_way(&mut gamestate);
+        assert_eq!(
+            gamestate.push_wall(2, 1).map(PushWall::state),
+            Some(PushWallState::Idle)
+        );
+    }
+
+    #[test]
+    fn rays_hit_the_wall_while_it_moves() {
+        let mut gamestate = corridor("...#");
+        assert_eq!(
+            gamestate.cast_ray((96.0, 96.0), 0.0, 400.0),
+            (128.0, 96.0, true)
+        );
+
+        gamestate.push();
+        gamestate.update_push_walls(PushWall::CELL_TIME / 4);
+        assert_eq!(
+            gamestate.cast_ray((96.0, 96.0), 0.0, 400.0),
+            (144.0, 96.0, true)
+        );
+        gamestate.update_push_walls(PushWall::CELL_TIME);
+        assert_eq!(
+            gamestate.cast_ray((96.0, 96.0), 0.0, 400.0),
+            (208.0, 96.0, true)
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use std::fs;
+use std::path::Path;
+
+use crate::Gamestate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    TurnLeft,
+    TurnRight,
+    Fire,
+    Use,
+}
+
+impl Action {
+    fn name(&self) -> &'static str {
+        match self {
+            Action::MoveForward => "forward",
+            Action::MoveBackward => "backward",
+            Action::TurnLeft => "left",
+            Action::TurnRight => "right",
+            Action::Fire => "fire",
+            Action::Use => "use",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        match name {
+            "forward" => Some(Action::MoveForward),
+            "backward" => Some(Action::MoveBackward),
+            "left" => Some(Action::TurnLeft),
+            "right" => Some(Action::TurnRight),
+            "fire" => Some(Action::Fire),
+            "use" => Some(Action::Use),
+            _ => None,
+        }
+    }
+}
+
+/// Everything the player did during one iteration of the game loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TickInput {
+    pub delta_time: u128,
+    pub actions: Vec<Action>,
+}
+
+impl TickInput {
+    pub fn new(delta_time: u128) -> TickInput {
+        TickInput {
+            delta_time,
+            actions: Vec::new(),
+        }
+    }
+}
+
+/// A recorded session: the seed and map it was played on, the input of every
+/// tick and the player pose it ended in.
+///
+/// Replaying the ticks on a fresh `Gamestate` built from the same map and seed
+/// has to end in exactly the same pose, which makes recordings usable as
+/// regression tests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    seed: u64,
+    map_id: String,
+    ticks: Vec<TickInput>,
+    final_pose: Option<(f32, f32, f32)>,
+}
+
+impl Recording {
+    const HEADER: &'static str = "raycaster-recording";
+    const VERSION: u32 = 1;
+
+    pub fn new(seed: u64, map_id: &str) -> Recording {
+        Recording {
+            seed,
+            map_id: map_id.to_string(),
+            ticks: Vec::new(),
+            final_pose: None,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn map_id(&self) -> &str {
+        &self.map_id
+    }
+    pub fn ticks(&self) -> &Vec<TickInput> {
+        &self.ticks
+    }
+    pub fn final_pose(&self) -> Option<(f32, f32, f32)> {
+        self.final_pose
+    }
+
+    pub fn push(&mut self, tick: TickInput) {
+        self.ticks.push(tick);
+    }
+
+    /// Stores the pose the game ended in, so playback can detect desyncs.
+    pub fn finish(&mut self, gamestate: &Gamestate) {
+        let (x, y) = gamestate.player_position();
+        self.final_pose = Some((x, y, gamestate.player_rotation()));
+    }
+
+    /// Feeds every recorded tick into `gamestate` and checks that it ends up
+    /// where the recorded session did.
+    pub fn replay(&self, gamestate: &mut Gamestate) -> Result<(), String> {
+        gamestate.set_seed(self.seed);
+        for tick in &self.ticks {
+            gamestate.apply_input(tick);
+        }
+        self.verify(gamestate)
+    }
+
+    pub fn verify(&self, gamestate: &Gamestate) -> Result<(), String> {
+        let (x, y) = gamestate.player_position();
+        let pose = (x, y, gamestate.player_rotation());
+        match self.final_pose {
+            Some(expected) if expected != pose => Err(format!(
+                "Playback diverged: expected pose {expected:?}, got {pose:?}"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut out = format!(
+            "{} {}\nseed {}\nmap {}\n",
+            Recording::HEADER,
+            Recording::VERSION,
+            self.seed,
+            self.map_id
+        );
+        for tick in &self.ticks {
+            let actions = if tick.actions.is_empty() {
+                "-".to_string()
+            } else {
+                tick.actions
+                    .iter()
+                    .map(|a| a.name())
+                    .collect::<Vec<&str>>()
+                    .join(",")
+            };
+            out.push_str(&format!("tick {} {}\n", tick.delta_time, actions));
+        }
+        if let Some((x, y, rotation)) = self.final_pose {
+            out.push_str(&format!("end {x} {y} {rotation}\n"));
+        }
+
+        fs::write(path, out).map_err(|e| format!("Could not write {}: {e}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Recording, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        Recording::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Recording, String> {
+        let mut lines = text.lines().enumerate();
+
+        let expected_header = format!("{} {}", Recording::HEADER, Recording::VERSION);
+        match lines.next() {
+            Some((_, header)) if header.trim() == expected_header => {}
+            _ => return Err(format!("Not a recording, expected '{expected_header}'")),
+        }
+
+        let mut recording = Recording::new(0, "");
+        for (i, line) in lines {
+            let line_no = i + 1;
+            let bad_line = || format!("Malformed recording line {line_no}: '{line}'");
+
+            let mut words = line.split_whitespace();
+            match words.next() {
+                None => {}
+                Some("seed") => {
+                    recording.seed = words
+                        .next()
+                        .and_then(|s| s.parse().ok())
+                        .ok_or_else(bad_line)?;
+                }
+                Some("map") => {
+                    recording.map_id = words.next().ok_or_else(bad_line)?.to_string();
+                }
+                Some("tick") => {
+                    let delta_time = words
+                        .next()
+                        .and_then(|s| s.parse().ok())
+                        .ok_or_else(bad_line)?;
+                    let mut tick = TickInput::new(delta_time);
+                    match words.next() {
+                        Some("-") => {}
+                        Some(actions) => {
+                            for name in actions.split(',') {
+                                tick.actions
+                                    .push(Action::from_name(name).ok_or_else(bad_line)?);
+                            }
+                        }
+                        None => return Err(bad_line()),
+                    }
+                    recording.ticks.push(tick);
+                }
+                Some("end") => {
+                    let values = words
+                        .map(|s| s.parse::<f32>())
+                        .collect::<Result<Vec<f32>, _>>()
+                        .map_err(|_| bad_line())?;
+                    match values[..] {
+                        [x, y, rotation] => recording.final_pose = Some((x, y, rotation)),
+                        _ => return Err(bad_line()),
+                    }
+                }
+                Some(_) => return Err(bad_line()),
+            }
+        }
+
+        Ok(recording)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> Gamestate {
+        Gamestate::from_map(
+            "\
+########
+#P.....#
+#......#
+#......#
+#.....E#
+########",
+        )
+    }
+
+    #[test]
+    fn replay_matches_recorded_session() {
+        let mut played = game();
+        let start = played.player_position();
+        played.set_seed(42);
+        let mut recording = Recording::new(42, "test");
+        let pattern = [
+            vec![Action::TurnLeft],
+            vec![Action::MoveForward],
+            vec![Action::MoveForward, Action::Fire],
+            vec![],
+            vec![Action::TurnRight, Action::MoveBackward],
+        ];
+        for i in 0..200 {
+            let tick = TickInput {
+                delta_time: 16_000_000 + i % 3 * 1_000_000,
+                actions: pattern[i as usize % pattern.len()].clone(),
+            };
+            played.apply_input(&tick);
+            recording.push(tick);
+        }
+        recording.finish(&played);
+        assert_ne!(played.player_position(), start);
+
+        let recording = Recording::parse(&recording_text(&recording)).unwrap();
+        let mut replayed = game();
+        recording.replay(&mut replayed).unwrap();
+
+        assert_eq!(replayed.player_position(), played.player_position());
+        assert_eq!(replayed.player_rotation(), played.player_rotation());
+        assert_eq!(replayed.entities(), played.entities());
+        assert_eq!(replayed.weapon(), played.weapon());
+    }
+
+    /// Goes through the file format, like a recording played from disk.
+    fn recording_text(recording: &Recording) -> String {
+        let path = crate::temp_path("recording.txt");
+        recording.save(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        text
+    }
+}
@@ -0,0 +1,590 @@
+use std::fs;
+use std::path::Path;
+
+use crate::ai::{AiState, EnemyAi};
+use crate::door::DoorState;
+use crate::entity::{Behaviour, Entities, Entity, EntityId};
+use crate::item::{Inventory, Item};
+use crate::map::Map;
+use crate::push_wall::PushWallState;
+use crate::rng::Rng;
+use crate::weapon::Weapon;
+use crate::Gamestate;
+
+impl Gamestate {
+    const SAVE_HEADER: &'static str = "raycaster-save";
+    pub const SAVE_VERSION: u32 = 9;
+
+    /// Writes the whole game to a text file: the map with its materials,
+    /// explored cells, doors and push-walls, the player's pose, health, weapon
+    /// and inventory, the spawn point, the entities, the seed and where the
+    /// random numbers are at. The first line names the format version, files
+    /// written by any other version are rejected by `load_game` instead of
+    /// being half understood.
+    pub fn save_game(&self, path: &Path) -> Result<(), String> {
+        let (x, y) = self.player.position();
+        let mut out = format!(
+            "{} {}\nseed {}\nblock_size {}\nplayer {x} {y} {} {} {}\nspawn {} {}\nmap {} {}\n",
+            Gamestate::SAVE_HEADER,
+            Gamestate::SAVE_VERSION,
+            self.seed,
+            self.block_size,
+            self.player.view_direction(),
+            self.player.health(),
+            self.player.armor(),
+            self.spawn_point.0,
+            self.spawn_point.1,
+            self.map.width(),
+            self.map.height()
+        );
+
+        let width = self.map.width() as usize;
+        let cells = width * self.map.height() as usize;
+        let mut walls = vec![false; cells];
+        for wall in self.map.walls() {
+            walls[*wall as usize] = true;
+        }
+        for row in 0..self.map.height() as usize {
+            let cells = (row * width..(row + 1) * width)
+                .map(|cell| match self.map.door(cell as u16) {
+                    Some(door) => match door.key {
+                        Some(key) => (b'a' + key - 1) as char,
+                        None => 'D',
+                    },
+                    None if self.map.push_wall(cell as u16).is_some() => 'S',
+                    None => match (walls[cell], self.map.materials()[cell]) {
+                        (false, _) => '.',
+                        (true, 0) => '#',
+                        (true, material) => (b'0' + material) as char,
+                    },
+                })
+                .collect::<String>();
+            out.push_str(&format!("row {cells}\n"));
+        }
+
+        for cell in self.map.door_cells() {
+            if let Some(door) = self.map.door(cell) {
+                let state = match door.state() {
+                    DoorState::Closed => "closed".to_string(),
+                    DoorState::Opening => "opening".to_string(),
+                    DoorState::Open { open_for } => format!("open {open_for}"),
+                    DoorState::Closing => "closing".to_string(),
+                };
+                let axis = if door.along_x() { "x" } else { "y" };
+                out.push_str(&format!("door {cell} {axis} {} {state}\n", door.openness()));
+            }
+        }
+        for cell in self.map.push_wall_cells() {
+            let state = match self.map.push_wall(cell).map(|wall| wall.state()) {
+                Some(PushWallState::Moving {
+                    direction: (dx, dy),
+                    offset,
+                    cells_left,
+                }) => format!("moving {dx} {dy} {offset} {cells_left}"),
+                Some(PushWallState::Stopped) => "stopped".to_string(),
+                _ => continue,
+            };
+            out.push_str(&format!("push_wall {cell} {state}\n"));
+        }
+        let close_after = self
+            .doors_close_after
+            .map_or("-".to_string(), |close_after| close_after.to_string());
+        out.push_str(&format!("doors_close_after {close_after}\n"));
+
+        let explored = self
+            .map
+            .explored()
+            .iter()
+            .map(|explored| if *explored { '1' } else { '0' })
+            .collect::<String>();
+        out.push_str(&format!("explored {explored}\n"));
+
+        let inventory = self.player.inventory();
+        let keys = inventory
+            .keys
+            .iter()
+            .map(|key| format!(" {key}"))
+            .collect::<String>();
+        out.push_str(&format!("inventory {}{keys}\n", inventory.score));
+
+        if let Some(respawn_in) = self.respawn_in {
+            out.push_str(&format!("respawn {respawn_in}\n"));
+        }
+
+        let weapon = &self.weapon;
+        out.push_str(&format!(
+            "rng {}\nweapon {} {} {} {} {} {}\n",
+            self.rng.state(),
+            weapon.damage,
+            weapon.fire_interval,
+            weapon.spread,
+            weapon.range,
+            weapon.ammo,
+            weapon.cooldown
+        ));
+
+        out.push_str(&format!("next_entity {}\n", self.entities.next_id()));
+        for (EntityId(id), entity) in self.entities.iter() {
+            let (x, y) = entity.position;
+            let behaviour = match &entity.behaviour {
+                Behaviour::Static => "static".to_string(),
+                Behaviour::Spin { degrees_per_second } => format!("spin {degrees_per_second}"),
+                Behaviour::Pickup(item) => match item {
+                    Item::Health(amount) => format!("pickup health {amount}"),
+                    Item::Ammo(rounds) => format!("pickup ammo {rounds}"),
+                    Item::Key(key) => format!("pickup key {key}"),
+                    Item::Score(points) => format!("pickup score {points}"),
+                },
+                Behaviour::Enemy(enemy) => {
+                    let state = match enemy.state {
+                        AiState::Patrol { waypoint } => format!("patrol {waypoint}"),
+                        AiState::Chase {
+                            last_seen: (x, y),
+                            unseen_for,
+                        } => format!("chase {x} {y} {unseen_for}"),
+                    };
+                    let waypoints = enemy
+                        .waypoints
+                        .iter()
+                        .map(|(x, y)| format!(" {x} {y}"))
+                        .collect::<String>();
+                    format!(
+                        "enemy {} {} {} {} {} {state}{waypoints}",
+                        enemy.speed,
+                        enemy.view_distance,
+                        enemy.view_cone,
+                        enemy.lose_track_after,
+                        enemy.damage_per_second
+                    )
+                }
+            };
+            let health = entity
+                .health
+                .map_or("-".to_string(), |health| health.to_string());
+            out.push_str(&format!(
+                "entity {id} {} {x} {y} {} {} {health} {behaviour}\n",
+                entity.sprite, entity.facing, entity.radius
+            ));
+        }
+
+        fs::write(path, out).map_err(|e| format!("Could not write {}: {e}", path.display()))
+    }
+
+    /// Replaces the game with the one saved in `path`. Nothing changes if the
+    /// file can't be read or was written by another version.
+    pub fn load_game(&mut self, path: &Path) -> Result<(), String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        let mut lines = text.lines().enumerate();
+
+        let header = lines
+            .next()
+            .map(|(_, header)| header.split_whitespace().collect::<Vec<&str>>())
+            .unwrap_or_default();
+        match header[..] {
+            [Gamestate::SAVE_HEADER, version] if version == Gamestate::SAVE_VERSION.to_string() => {
+            }
+            [Gamestate::SAVE_HEADER, version] => {
+                return Err(format!(
+                    "{} was saved with format version {version}, only version {} can be loaded",
+                    path.display(),
+                    Gamestate::SAVE_VERSION
+                ))
+            }
+            _ => return Err(format!("{} is not a save file", path.display())),
+        }
+
+        let mut seed = None;
+        let mut block_size = None;
+        let mut pose = None;
+        let mut size = None;
+        let mut rows = Vec::new();
+        let mut explored = None;
+        let mut vitals = None;
+        let mut spawn_point = None;
+        let mut respawn_in = None;
+        let mut doors = Vec::new();
+        let mut push_walls = Vec::new();
+        let mut doors_close_after = None;
+        let mut inventory = None;
+        let mut rng = None;
+        let mut weapon = None;
+        let mut next_entity = None;
+        let mut entities = Vec::new();
+        for (i, line) in lines {
+            let line_no = i + 1;
+            let bad_line = || format!("Malformed save line {line_no}: '{line}'");
+
+            let mut words = line.split_whitespace();
+            match words.next() {
+                None => {}
+                Some("seed") => {
+                    seed = Some(parse(words.next()).ok_or_else(bad_line)?);
+                }
+                Some("block_size") => {
+                    block_size = Some(parse(words.next()).ok_or_else(bad_line)?);
+                }
+                Some("player") => {
+                    let values = words
+                        .map(|s| s.parse::<f32>())
+                        .collect::<Result<Vec<f32>, _>>()
+                        .map_err(|_| bad_line())?;
+                    match values[..] {
+                        [x, y, rotation, health, armor] => {
+                            pose = Some((x, y, rotation));
+                            vitals = Some((health, armor));
+                        }
+                        _ => return Err(bad_line()),
+                    }
+                }
+                Some("map") => {
+                    let width: u8 = parse(words.next()).ok_or_else(bad_line)?;
+                    let height: u8 = parse(words.next()).ok_or_else(bad_line)?;
+                    size = Some((width, height));
+                }
+                Some("row") => rows.push(words.next().ok_or_else(bad_line)?),
+                Some("explored") => {
+                    explored = Some(
+                        words
+                            .next()
+                            .ok_or_else(bad_line)?
+                            .chars()
+                            .map(|c| match c {
+                                '0' => Ok(false),
+                                '1' => Ok(true),
+                                _ => Err(bad_line()),
+                            })
+                            .collect::<Result<Vec<bool>, String>>()?,
+                    );
+                }
+                Some("spawn") => {
+                    let x = parse(words.next()).ok_or_else(bad_line)?;
+                    let y = parse(words.next()).ok_or_else(bad_line)?;
+                    spawn_point = Some((x, y));
+                }
+                Some("door") => {
+                    let cell: u16 = parse(words.next()).ok_or_else(bad_line)?;
+                    let along_x = match words.next() {
+                        Some("x") => true,
+                        Some("y") => false,
+                        _ => return Err(bad_line()),
+                    };
+                    let openness: f32 = parse(words.next()).ok_or_else(bad_line)?;
+                    let state = match words.next() {
+                        Some("closed") => DoorState::Closed,
+                        Some("opening") => DoorState::Opening,
+                        Some("open") => DoorState::Open {
+                            open_for: parse(words.next()).ok_or_else(bad_line)?,
+                        },
+                        Some("closing") => DoorState::Closing,
+                        _ => return Err(bad_line()),
+                    };
+                    doors.push((cell, along_x, openness, state));
+                }
+                Some("push_wall") => {
+                    let cell: u16 = parse(words.next()).ok_or_else(bad_line)?;
+                    let state = match words.next() {
+                        Some("moving") => PushWallState::Moving {
+                            direction: (
+                                parse(words.next()).ok_or_else(bad_line)?,
+                                parse(words.next()).ok_or_else(bad_line)?,
+                            ),
+                            offset: parse(words.next()).ok_or_else(bad_line)?,
+                            cells_left: parse(words.next()).ok_or_else(bad_line)?,
+                        },
+                        Some("stopped") => PushWallState::Stopped,
+                        _ => return Err(bad_line()),
+                    };
+                    push_walls.push((cell, state));
+                }
+                Some("doors_close_after") => {
+                    doors_close_after = match words.next() {
+                        Some("-") => Some(None),
+                        word => Some(Some(parse(word).ok_or_else(bad_line)?)),
+                    };
+                }
+                Some("inventory") => {
+                    let score = parse(words.next()).ok_or_else(bad_line)?;
+                    let keys = words
+                        .map(|s| s.parse::<u8>())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| bad_line())?;
+                    inventory = Some(Inventory { keys, score });
+                }
+                Some("respawn") => {
+                    respawn_in = Some(parse(words.next()).ok_or_else(bad_line)?);
+                }
+                Some("rng") => {
+                    rng = Some(Rng::new(parse(words.next()).ok_or_else(bad_line)?));
+                }
+                Some("weapon") => {
+                    weapon = Some(Weapon {
+                        damage: parse(words.next()).ok_or_else(bad_line)?,
+                        fire_interval: parse(words.next()).ok_or_else(bad_line)?,
+                        spread: parse(words.next()).ok_or_else(bad_line)?,
+                        range: parse(words.next()).ok_or_else(bad_line)?,
+                        ammo: parse(words.next()).ok_or_else(bad_line)?,
+                        cooldown: parse(words.next()).ok_or_else(bad_line)?,
+                    });
+                }
+                Some("next_entity") => {
+                    next_entity = Some(parse(words.next()).ok_or_else(bad_line)?);
+                }
+                Some("entity") => {
+                    let id = parse(words.next()).ok_or_else(bad_line)?;
+                    let sprite = parse(words.next()).ok_or_else(bad_line)?;
+                    let x = parse(words.next()).ok_or_else(bad_line)?;
+                    let y = parse(words.next()).ok_or_else(bad_line)?;
+                    let facing = parse(words.next()).ok_or_else(bad_line)?;
+                    let radius = parse(words.next()).ok_or_else(bad_line)?;
+                    let health = match words.next() {
+                        Some("-") => None,
+                        word => Some(parse(word).ok_or_else(bad_line)?),
+                    };
+                    let behaviour = match words.next() {
+                        Some("static") => Behaviour::Static,
+                        Some("spin") => Behaviour::Spin {
+                            degrees_per_second: parse(words.next()).ok_or_else(bad_line)?,
+                        },
+                        Some("pickup") => {
+                            let item = match words.next() {
+                                Some("health") => {
+                                    Item::Health(parse(words.next()).ok_or_else(bad_line)?)
+                                }
+                                Some("ammo") => {
+                                    Item::Ammo(parse(words.next()).ok_or_else(bad_line)?)
+                                }
+                                Some("key") => Item::Key(parse(words.next()).ok_or_else(bad_line)?),
+                                Some("score") => {
+                                    Item::Score(parse(words.next()).ok_or_else(bad_line)?)
+                                }
+                                _ => return Err(bad_line()),
+                            };
+                            Behaviour::Pickup(item)
+                        }
+                        Some("enemy") => {
+                            let speed = parse(words.next()).ok_or_else(bad_line)?;
+                            let view_distance = parse(words.next()).ok_or_else(bad_line)?;
+                            let view_cone = parse(words.next()).ok_or_else(bad_line)?;
+                            let lose_track_after = parse(words.next()).ok_or_else(bad_line)?;
+                            let damage_per_second = parse(words.next()).ok_or_else(bad_line)?;
+                            let state = match words.next() {
+                                Some("patrol") => AiState::Patrol {
+                                    waypoint: parse(words.next()).ok_or_else(bad_line)?,
+                                },
+                                Some("chase") => AiState::Chase {
+                                    last_seen: (
+                                        parse(words.next()).ok_or_else(bad_line)?,
+                                        parse(words.next()).ok_or_else(bad_line)?,
+                                    ),
+                                    unseen_for: parse(words.next()).ok_or_else(bad_line)?,
+                                },
+                                _ => return Err(bad_line()),
+                            };
+                            let values = words
+                                .by_ref()
+                                .map(|s| s.parse::<f32>())
+                                .collect::<Result<Vec<f32>, _>>()
+                                .map_err(|_| bad_line())?;
+                            if values.len() % 2 != 0 {
+                                return Err(bad_line());
+                            }
+                            Behaviour::Enemy(EnemyAi {
+                                waypoints: values.chunks(2).map(|p| (p[0], p[1])).collect(),
+                                speed,
+                                view_distance,
+                                view_cone,
+                                lose_track_after,
+                                damage_per_second,
+                                state,
+                            })
+                        }
+                        _ => return Err(bad_line()),
+                    };
+                    entities.push((
+                        EntityId(id),
+                        Entity {
+                            position: (x, y),
+                            facing,
+                            radius,
+                            health,
+                            sprite,
+                            behaviour,
+                        },
+                    ));
+                }
+                Some(_) => return Err(bad_line()),
+            }
+        }
+
+        let missing = |what: &str| format!("{} has no {what}", path.display());
+        let seed = seed.ok_or_else(|| missing("seed"))?;
+        let block_size = block_size.ok_or_else(|| missing("block size"))?;
+        let (x, y, rotation) = pose.ok_or_else(|| missing("player"))?;
+        let (health, armor) = vitals.ok_or_else(|| missing("player"))?;
+        let spawn_point = spawn_point.ok_or_else(|| missing("spawn point"))?;
+        let (width, height) = size.ok_or_else(|| missing("map size"))?;
+        let (mut map, _, _) = Map::parse(&rows.join("\n"))?;
+        if (map.width(), map.height()) != (width, height) {
+            return Err(format!(
+                "The map in {} is {}x{}, expected {width}x{height}",
+                path.display(),
+                map.width(),
+                map.height()
+            ));
+        }
+        map.set_explored(explored.ok_or_else(|| missing("explored cells"))?)?;
+        for (cell, along_x, openness, state) in doors {
+            map.door_mut(cell)
+                .ok_or_else(|| format!("Cell {cell} in {} is not a door", path.display()))?
+                .restore(along_x, openness, state);
+        }
+        for (cell, state) in push_walls {
+            map.push_wall_mut(cell)
+                .ok_or_else(|| format!("Cell {cell} in {} is not a push-wall", path.display()))?
+                .restore(state);
+        }
+        let doors_close_after = doors_close_after.ok_or_else(|| missing("door closing time"))?;
+        let inventory = inventory.ok_or_else(|| missing("inventory"))?;
+        let rng = rng.ok_or_else(|| missing("random number state"))?;
+        let weapon = weapon.ok_or_else(|| missing("weapon"))?;
+        let next_entity = next_entity.ok_or_else(|| missing("next entity id"))?;
+        if let Some((EntityId(id), _)) =
+            entities.iter().find(|(EntityId(id), _)| *id >= next_entity)
+        {
+            return Err(format!(
+                "Entity {id} in {} is not below the next entity id {next_entity}",
+                path.display()
+            ));
+        }
+
+        self.seed = seed;
+        self.block_size = block_size;
+        self.doors_close_after = doors_close_after;
+        self.rng = rng;
+        self.weapon = weapon;
+        self.shots.clear();
+        self.events.clear();
+        self.spawn_point = spawn_point;
+        self.respawn_in = respawn_in;
+        self.player.set_health(health);
+        self.player.set_armor(armor);
+        *self.player.inventory_mut() = inventory;
+        self.map = map;
+        self.entities = Entities::restore(next_entity, entities);
+        self.player.set_position(x, y);
+        self.player.set_view_direction(rotation);
+        Ok(())
+    }
+}
+
+fn parse<T: std::str::FromStr>(word: Option<&str>) -> Option<T> {
+    word.and_then(|word| word.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{temp_path, Action, TickInput};
+
+    /// A game with a bit of everything in it, some of it halfway through
+    /// changing.
+    fn game() -> Gamestate {
+        let mut gamestate = Gamestate::from_map(
+            "\
+#########
+#PK.D...#
+#1.#a#..#
+#..S...E#
+#########",
+        );
+        gamestate.set_seed(9);
+        let mut spinner = Entity::new((352.0, 96.0), 1);
+        spinner.behaviour = Behaviour::Spin {
+            degrees_per_second: 30.0,
+        };
+        gamestate.spawn_entity(spinner);
+
+        // Opens the door and pushes the push-wall, both east of the player.
+        gamestate.player.set_view_direction(181.0);
+        for (x, y) in [(3, 1), (2, 3)] {
+            gamestate.teleport(x, y).unwrap();
+            gamestate.apply_input(&TickInput {
+                delta_time: 300_000_000,
+                actions: vec![Action::Use, Action::Fire],
+            });
+        }
+        let rays = gamestate.cast_rays();
+        gamestate.explore(&rays);
+        gamestate.inventory_mut().score = 120;
+        gamestate
+    }
+
+    #[test]
+    fn save_load_save_gives_the_same_text() {
+        let (first, second) = (temp_path("round-trip-1.sav"), temp_path("round-trip-2.sav"));
+        let saved = game();
+        saved.save_game(&first).unwrap();
+
+        let mut loaded = Gamestate::from_map("#.#");
+        loaded.load_game(&first).unwrap();
+        loaded.save_game(&second).unwrap();
+
+        let (first_text, second_text) = (
+            fs::read_to_string(&first).unwrap(),
+            fs::read_to_string(&second).unwrap(),
+        );
+        fs::remove_file(&first).unwrap();
+        fs::remove_file(&second).unwrap();
+        assert_eq!(first_text, second_text);
+        assert_eq!(loaded.player_position(), saved.player_position());
+        assert_eq!(loaded.entities(), saved.entities());
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let path = temp_path("old-version.sav");
+        game().save_game(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap().replacen(
+            &format!("raycaster-save {}", Gamestate::SAVE_VERSION),
+            &format!("raycaster-save {}", Gamestate::SAVE_VERSION - 1),
+            1,
+        );
+        fs::write(&path, text).unwrap();
+
+        let mut gamestate = Gamestate::from_map("#.#");
+        let result = gamestate.load_game(&path);
+        fs::remove_file(&path).unwrap();
+        let error = result.unwrap_err();
+        let version = format!("format version {}", Gamestate::SAVE_VERSION - 1);
+        assert!(error.contains(&version), "{error}");
+        assert_eq!(gamestate.map_width(), 3);
+        assert_eq!(gamestate.player_position(), (96.0, 32.0));
+    }
+
+    #[test]
+    fn doors_keep_their_axis_when_a_push_wall_moved_next_to_them() {
+        let mut saved = Gamestate::from_map(
+            "\
+#######
+#..P..#
+#..S..#
+#...D##
+#######",
+        );
+        // Pushes the wall south, right next to the door.
+        saved.player.set_view_direction(270.0);
+        saved.push();
+        saved.update_push_walls(crate::PushWall::CELL_TIME * 2);
+        assert_eq!(saved.push_wall_cells(), vec![24]);
+
+        let path = temp_path("door-axis.sav");
+        saved.save_game(&path).unwrap();
+        let mut loaded = Gamestate::from_map("#.#");
+        let result = loaded.load_game(&path);
+        fs::remove_file(&path).unwrap();
+        result.unwrap();
+        assert!(!saved.door(4, 3).unwrap().along_x());
+        assert_eq!(loaded.door(4, 3), saved.door(4, 3));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::entity::EntityId;
+use crate::health::DamageSource;
+use crate::Gamestate;
+
+/// A hitscan weapon: a shot instantly hits whatever is first along a ray.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weapon {
+    /// Taken off the health of an entity that is hit.
+    pub damage: f32,
+    /// Time between two shots, in nanoseconds.
+    pub fire_interval: u128,
+    /// Shots go off in a random direction up to half of this many degrees to
+    /// either side of the view direction.
+    pub spread: f32,
+    /// How far a shot reaches, in world units.
+    pub range: f32,
+    pub ammo: u32,
+    /// Time until the next shot is possible, in nanoseconds.
+    pub(crate) cooldown: u128,
+}
+
+impl Default for Weapon {
+    fn default() -> Weapon {
+        Weapon {
+            damage: 25.0,
+            fire_interval: 400_000_000,
+            spread: 2.0,
+            range: 1000.0,
+            ammo: 50,
+            cooldown: 0,
+        }
+    }
+}
+
+impl Weapon {
+    /// Whether the next shot would go off.
+    pub fn ready(&self) -> bool {
+        self.cooldown == 0 && self.ammo > 0
+    }
+}
+
+/// What a shot hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hit {
+    /// Nothing within the weapon's range.
+    Nothing,
+    Wall,
+    /// `killed` is set when the shot took the entity's last health, in which
+    /// case it is already despawned.
+    Entity {
+        id: EntityId,
+        killed: bool,
+    },
+}
+
+/// A shot that went off, for showing impact effects.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shot {
+    /// Where the shot ended, in world units.
+    pub point: (f32, f32),
+    pub hit: Hit,
+}
+
+impl Gamestate {
+    pub fn weapon(&self) -> &Weapon {
+        &self.weapon
+    }
+    pub fn weapon_mut(&mut self) -> &mut Weapon {
+        &mut self.weapon
+    }
+
+    /// The shots fired since this was last called, oldest first.
+    pub fn take_shots(&mut self) -> Vec<Shot> {
+        std::mem::take(&mut self.shots)
+    }
+
+    /// Fires the weapon from the player's position along their view direction,
+    /// unless it is still cooling down or out of ammo. Hits the nearest wall
+    /// or entity with health and damages the entity, see `damage_entity`.
+    /// Pickups and props without health don't stop shots.
+    pub fn fire(&mut self) -> Option<Shot> {
+        if !self.weapon.ready() {
+            return None;
+        }
+        self.weapon.ammo -= 1;
+        self.weapon.cooldown = self.weapon.fire_interval;
+
+        let spread = (self.rng.next_f32() - 0.5) * self.weapon.spread;
+        let angle = self.player.facing() + spread;
+        let origin = self.player.position();
+        let (wall_x, wall_y, hit_wall) = self.cast_ray(origin, angle, self.weapon.range);
+        let (sin, cos) = angle.to_radians().sin_cos();
+
+        let wall_distance = ((wall_x - origin.0).powi(2) + (wall_y - origin.1).powi(2)).sqrt();
+        let entity = self
+            .entities
+            .iter()
+            .filter(|(_, entity)| entity.health.is_some())
+            .filter_map(|(id, entity)| {
+                ray_circle_distance(origin, (cos, sin), entity.position, entity.radius)
+                    .map(|distance| (distance, id))
+            })
+            .filter(|(distance, _)| *distance <= wall_distance)
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        let shot = match entity {
+            Some((distance, id)) => {
+                let killed = self.damage_entity(id, self.weapon.damage, DamageSource::Player);
+                Shot {
+                    point: (origin.0 + cos * distance, origin.1 + sin * distance),
+                    hit: Hit::Entity { id, killed },
+                }
+            }
+            None => Shot {
+                point: (wall_x, wall_y),
+                hit: if hit_wall { Hit::Wall } else { Hit::Nothing },
+            },
+        };
+        Some(shot)
+    }
+}
+
+/// How far along a ray from `origin` in the unit `direction` it first touches
+/// a circle, `None` if it misses it or the circle is behind the origin.
+fn ray_circle_distance(
+    origin: (f32, f32),
+    direction: (f32, f32),
+    center: (f32, f32),
+    radius: f32,
+) -> Option<f32> {
+    let (mx, my) = (origin.0 - center.0, origin.1 - center.1);
+    let b = mx * direction.0 + my * direction.1;
+    let c = mx * mx + my * my - radius * radius;
+    if c > 0.0 && b > 0.0 {
+        return None;
+    }
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    Some((-b - discriminant.sqrt()).max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Behaviour, Entity, Item};
+
+    #[test]
+    fn shots_pass_through_entities_without_health() {
+        let mut gamestate = Gamestate::from_map(
+            "\
+#######
+#.....#
+#######",
+        );
+        gamestate.teleport(1, 1).unwrap();
+        // Looking east, along the corridor.
+        gamestate.player.set_view_direction(180.0);
+        gamestate.weapon_mut().spread = 0.0;
+        let mut pickup = Entity::new((160.0, 96.0), 3);
+        pickup.behaviour = Behaviour::Pickup(Item::Score(10));
+        let pickup = gamestate.spawn_entity(pickup);
+        let mut enemy = Entity::new((288.0, 96.0), 2);
+        enemy.health = Some(100.0);
+        let enemy = gamestate.spawn_entity(enemy);
+
+        let shot = gamestate.fire().unwrap();
+        assert_eq!(
+            shot.hit,
+            Hit::Entity {
+                id: enemy,
+                killed: false
+            }
+        );
+        assert_eq!(gamestate.entities().get(enemy).unwrap().health, Some(75.0));
+        assert!(gamestate.entities().get(pickup).is_some());
+    }
+}
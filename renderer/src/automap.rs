@@ -0,0 +1,68 @@
+use crate::geometry::Rect;
+use crate::minimap::{Minimap, PlayerMarker};
+
+/// A full screen map overlay, centered on the player, that can be panned and
+/// zoomed around the level.
+pub struct Automap {
+    visible: bool,
+    view: Minimap,
+}
+
+impl Automap {
+    const DEFAULT_ZOOM: f32 = 40.0;
+    /// How far one call to `pan` moves the view, in screen pixels.
+    const PAN_STEP: f32 = 20.0;
+
+    pub fn new() -> Automap {
+        let mut view = Minimap::new(Rect::new(0, 0, 1, 1), Automap::DEFAULT_ZOOM);
+        view.set_follow_player(true);
+        view.set_show_grid(true);
+        view.set_show_rays(false);
+        view.set_player_marker(PlayerMarker::Arrow);
+
+        Automap {
+            visible: false,
+            view,
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Moves the view by one step in the given direction, `-1`, `0` or `1` on
+    /// each axis.
+    pub fn pan(&mut self, x_direction: f32, y_direction: f32) {
+        let step = Automap::PAN_STEP / self.view.zoom();
+        let (x, y) = self.view.pan();
+        self.view
+            .set_pan((x + x_direction * step, y + y_direction * step));
+    }
+
+    pub fn zoom(&mut self, factor: f32) {
+        self.view.set_zoom(self.view.zoom() * factor);
+    }
+
+    /// Centers the view back on the player at the default zoom.
+    pub fn reset(&mut self) {
+        self.view.set_pan((0.0, 0.0));
+        self.view.set_zoom(Automap::DEFAULT_ZOOM);
+    }
+
+    pub(crate) fn view(&mut self, frame: Rect) -> &Minimap {
+        self.view.set_frame(frame);
+        &self.view
+    }
+}
+
+impl Default for Automap {
+    fn default() -> Automap {
+        Automap::new()
+    }
+}
@@ -0,0 +1,89 @@
+use crate::framebuffer::Framebuffer;
+use crate::geometry::{Color, Point, Rect};
+
+/// The drawing primitives the renderer needs from an output.
+///
+/// Everything the renderer draws, from the minimap to the 3D view, is built
+/// from these calls, so implementing this trait is all it takes to render to a
+/// new kind of output.
+pub trait Backend {
+    /// Size of the output in pixels, before scaling.
+    fn size(&self) -> (u32, u32);
+
+    /// Scales the coordinates of every following draw call.
+    fn set_scale(&mut self, scale: f32) -> Result<(), String>;
+
+    fn clear(&mut self, color: Color) -> Result<(), String>;
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<(), String>;
+
+    fn fill_rects(&mut self, rects: &[Rect], color: Color) -> Result<(), String> {
+        for rect in rects {
+            self.fill_rect(*rect, color)?;
+        }
+        Ok(())
+    }
+
+    fn draw_line(&mut self, start: Point, end: Point, color: Color) -> Result<(), String>;
+
+    /// Stretches a column of texels over `dest`, the first texel ending up at
+    /// the top of it.
+    fn blit_column(&mut self, dest: Rect, texels: &[Color]) -> Result<(), String> {
+        if texels.is_empty() || dest.height == 0 {
+            return Ok(());
+        }
+
+        let texel_height = dest.height as f32 / texels.len() as f32;
+        let mut start = 0;
+        // Runs of the same color are drawn as a single rectangle.
+        for end in 1..=texels.len() {
+            if end < texels.len() && texels[end] == texels[start] {
+                continue;
+            }
+            let y_top = dest.y + (start as f32 * texel_height) as i32;
+            let y_bottom = dest.y + (end as f32 * texel_height) as i32;
+            if y_bottom > y_top {
+                self.fill_rect(
+                    Rect::new(dest.x, y_top, dest.width, (y_bottom - y_top) as u32),
+                    texels[start],
+                )?;
+            }
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// Copies a whole frame into `dest`, stretching it to fit.
+    fn blit_frame(&mut self, frame: &Framebuffer, dest: Rect) -> Result<(), String> {
+        let (width, height) = (frame.width(), frame.height());
+        for x in 0..width {
+            let x_start = dest.x + (x * dest.width / width) as i32;
+            let x_end = dest.x + ((x + 1) * dest.width / width) as i32;
+            if x_end == x_start {
+                continue;
+            }
+            let texels = (0..height)
+                .filter_map(|y| frame.pixel(x, y))
+                .collect::<Vec<Color>>();
+            self.blit_column(
+                Rect::new(x_start, dest.y, (x_end - x_start) as u32, dest.height),
+                &texels,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), String>;
+
+    /// The pixels drawn so far, for backends that keep them in memory.
+    fn framebuffer(&self) -> Option<&Framebuffer> {
+        None
+    }
+
+    /// Reads back what has been drawn so far, at the output resolution.
+    fn read_pixels(&mut self) -> Result<Framebuffer, String> {
+        self.framebuffer()
+            .cloned()
+            .ok_or_else(|| "This backend can not read back its pixels".to_string())
+    }
+}
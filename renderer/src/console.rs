@@ -0,0 +1,122 @@
+use crate::geometry::Color;
+use std::collections::VecDeque;
+
+/// The state of a drop-down console: the line being typed, the lines typed
+/// before and everything printed to it.
+///
+/// The console only collects input, running the commands is up to the game.
+pub struct Console {
+    open: bool,
+    input: String,
+    history: Vec<String>,
+    /// Which entry of the history is being shown while browsing it.
+    history_index: Option<usize>,
+    log: VecDeque<String>,
+}
+
+impl Console {
+    /// How many lines the log keeps.
+    pub const LOG_LENGTH: usize = 200;
+    /// How many lines of the log are shown at once.
+    pub(crate) const VISIBLE_LINES: usize = 12;
+    pub(crate) const BACKGROUND_COLOR: Color = Color::rgb(20, 20, 30);
+    pub(crate) const TEXT_COLOR: Color = Color::rgb(220, 220, 220);
+    pub(crate) const INPUT_COLOR: Color = Color::rgb(255, 230, 120);
+
+    pub fn new() -> Console {
+        Console {
+            open: false,
+            input: String::new(),
+            history: Vec::new(),
+            history_index: None,
+            log: VecDeque::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+    pub fn log(&self) -> &VecDeque<String> {
+        &self.log
+    }
+
+    /// Appends typed text to the input line.
+    pub fn type_text(&mut self, text: &str) {
+        self.input.push_str(text);
+    }
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    /// Takes the input line, adding it to the history and echoing it to the
+    /// log. Returns `None` if nothing was typed.
+    pub fn submit(&mut self) -> Option<String> {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+        self.history_index = None;
+        if line.is_empty() {
+            return None;
+        }
+        self.print(&format!("> {line}"));
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        Some(line)
+    }
+
+    /// Replaces the input line with the previous line in the history.
+    pub fn history_previous(&mut self) {
+        let index = match self.history_index {
+            Some(index) => index.saturating_sub(1),
+            None if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+        };
+        self.history_index = Some(index);
+        self.input = self.history[index].clone();
+    }
+
+    /// Replaces the input line with the next line in the history, or clears it
+    /// after the last one.
+    pub fn history_next(&mut self) {
+        match self.history_index {
+            Some(index) if index + 1 < self.history.len() => {
+                self.history_index = Some(index + 1);
+                self.input = self.history[index + 1].clone();
+            }
+            Some(_) => {
+                self.history_index = None;
+                self.input.clear();
+            }
+            None => {}
+        }
+    }
+
+    /// Adds text to the log, one entry per line.
+    pub fn print(&mut self, text: &str) {
+        for line in text.lines() {
+            if self.log.len() == Console::LOG_LENGTH {
+                self.log.pop_front();
+            }
+            self.log.push_back(line.to_string());
+        }
+    }
+
+    pub fn clear_log(&mut self) {
+        self.log.clear();
+    }
+}
+
+impl Default for Console {
+    fn default() -> Console {
+        Console::new()
+    }
+}
@@ -0,0 +1,32 @@
+use std::fmt;
+
+/// Everything that can go wrong in the renderer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RendererError {
+    /// The output could not be set up, like a failing SDL init or window.
+    Init(String),
+    /// Drawing to or reading from the output failed.
+    Draw(String),
+    /// A file the renderer reads or writes, like a theme or a screenshot,
+    /// could not be used.
+    Asset(String),
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RendererError::Init(s) => write!(f, "Could not set up rendering: {s}"),
+            RendererError::Draw(s) => write!(f, "Drawing failed: {s}"),
+            RendererError::Asset(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {}
+
+/// Lets functions that report errors as strings use `?` on renderer results.
+impl From<RendererError> for String {
+    fn from(error: RendererError) -> String {
+        error.to_string()
+    }
+}
@@ -0,0 +1,182 @@
+use crate::backend::Backend;
+use crate::error::RendererError;
+use crate::geometry::{Color, Point, Rect};
+use crate::image::{self, ImageFormat};
+use std::path::Path;
+
+/// An RGBA pixel buffer that the renderer can draw into without a window.
+#[derive(Clone)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    scale: f32,
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            scale: 1.0,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    /// Wraps existing RGBA pixel data, four bytes per pixel, row by row.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<u8>) -> Result<Framebuffer, String> {
+        if pixels.len() != (width * height * 4) as usize {
+            return Err(format!(
+                "Expected {} bytes of pixel data for {width}x{height}, got {}",
+                width * height * 4,
+                pixels.len()
+            ));
+        }
+        Ok(Framebuffer {
+            width,
+            height,
+            scale: 1.0,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// The raw pixel data, four bytes (r, g, b, a) per pixel, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = ((y * self.width + x) * 4) as usize;
+        Some(Color::rgba(
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ))
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        self.put_pixel(x as i64, y as i64, color);
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let x_start = ((rect.x as f32 * self.scale) as i64).max(0);
+        let y_start = ((rect.y as f32 * self.scale) as i64).max(0);
+        let x_end = ((rect.right() as f32 * self.scale) as i64).min(self.width as i64);
+        let y_end = ((rect.bottom() as f32 * self.scale) as i64).min(self.height as i64);
+
+        for y in y_start..y_end {
+            for x in x_start..x_end {
+                self.put_pixel(x, y, color);
+            }
+        }
+    }
+
+    pub fn draw_line(&mut self, start: Point, end: Point, color: Color) {
+        let (x0, y0) = (start.x as f32 * self.scale, start.y as f32 * self.scale);
+        let (x1, y1) = (end.x as f32 * self.scale, end.y as f32 * self.scale);
+
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as i64;
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            let x = x0 + (x1 - x0) * t;
+            let y = y0 + (y1 - y0) * t;
+            self.put_pixel(x as i64, y as i64, color);
+        }
+    }
+
+    /// Copies `frame` into `dest`, stretching it to fit.
+    pub fn blit(&mut self, frame: &Framebuffer, dest: Rect) {
+        let x_start = ((dest.x as f32 * self.scale) as i64).max(0);
+        let y_start = ((dest.y as f32 * self.scale) as i64).max(0);
+        let x_end = ((dest.right() as f32 * self.scale) as i64).min(self.width as i64);
+        let y_end = ((dest.bottom() as f32 * self.scale) as i64).min(self.height as i64);
+        let dest_width = dest.width as f32 * self.scale;
+        let dest_height = dest.height as f32 * self.scale;
+
+        for y in y_start..y_end {
+            let source_y = ((y as f32 - dest.y as f32 * self.scale) / dest_height
+                * frame.height as f32) as u32;
+            for x in x_start..x_end {
+                let source_x = ((x as f32 - dest.x as f32 * self.scale) / dest_width
+                    * frame.width as f32) as u32;
+                if let Some(color) = frame.pixel(source_x, source_y) {
+                    self.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// Writes the buffer as an image, the format being picked from the extension.
+    pub fn save(&self, path: &Path) -> Result<(), RendererError> {
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            RendererError::Asset(format!("Unsupported image format: {}", path.display()))
+        })?;
+        image::write_image(self, path, format).map_err(RendererError::Asset)
+    }
+
+    fn put_pixel(&mut self, x: i64, y: i64, color: Color) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let i = ((y as u32 * self.width + x as u32) * 4) as usize;
+        self.pixels[i..i + 4].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+}
+
+impl Backend for Framebuffer {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn set_scale(&mut self, scale: f32) -> Result<(), String> {
+        if scale <= 0.0 {
+            return Err(format!("Invalid scale: {scale}"));
+        }
+        self.scale = scale;
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), String> {
+        Framebuffer::clear(self, color);
+        Ok(())
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<(), String> {
+        Framebuffer::fill_rect(self, rect, color);
+        Ok(())
+    }
+
+    fn draw_line(&mut self, start: Point, end: Point, color: Color) -> Result<(), String> {
+        Framebuffer::draw_line(self, start, end, color);
+        Ok(())
+    }
+
+    fn blit_frame(&mut self, frame: &Framebuffer, dest: Rect) -> Result<(), String> {
+        self.blit(frame, dest);
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn framebuffer(&self) -> Option<&Framebuffer> {
+        Some(self)
+    }
+}
@@ -0,0 +1,287 @@
+use crate::framebuffer::Framebuffer;
+use std::io::Write;
+
+/// Writes animated GIFs frame by frame.
+///
+/// Every frame is mapped onto a fixed 256 color palette with 3 bits of red,
+/// 3 bits of green and 2 bits of blue, which is good enough for the flat
+/// colors the renderer draws and needs no per-frame quantization.
+pub struct GifEncoder<W: Write> {
+    writer: W,
+    width: u16,
+    height: u16,
+}
+
+impl<W: Write> GifEncoder<W> {
+    /// Writes the header, the palette and, when `looping`, the extension that
+    /// makes viewers repeat the animation forever.
+    pub fn new(
+        mut writer: W,
+        width: u32,
+        height: u32,
+        looping: bool,
+    ) -> Result<GifEncoder<W>, String> {
+        let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+            (Ok(width), Ok(height)) => (width, height),
+            _ => {
+                return Err(format!(
+                    "Frame size {width}x{height} is too large for a GIF"
+                ))
+            }
+        };
+
+        let mut header = Vec::new();
+        header.extend_from_slice(b"GIF89a");
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        // Global color table of 2^(7 + 1) entries, 8 bits per primary color.
+        header.extend_from_slice(&[0b1111_0111, 0, 0]);
+        for index in 0..=255u8 {
+            let (r, g, b) = palette_color(index);
+            header.extend_from_slice(&[r, g, b]);
+        }
+        if looping {
+            header.extend_from_slice(&[0x21, 0xff, 0x0b]);
+            header.extend_from_slice(b"NETSCAPE2.0");
+            header.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+        }
+
+        writer.write_all(&header).map_err(|e| e.to_string())?;
+        Ok(GifEncoder {
+            writer,
+            width,
+            height,
+        })
+    }
+
+    /// Appends a frame shown for `delay` hundredths of a second. The frame has
+    /// to be the same size as the animation.
+    pub fn add_frame(&mut self, frame: &Framebuffer, delay: u16) -> Result<(), String> {
+        if frame.width() != self.width as u32 || frame.height() != self.height as u32 {
+            return Err(format!(
+                "Frame is {}x{}, the animation is {}x{}",
+                frame.width(),
+                frame.height(),
+                self.width,
+                self.height
+            ));
+        }
+
+        let mut data = Vec::new();
+        // Graphic control extension, only used for the delay.
+        data.extend_from_slice(&[0x21, 0xf9, 0x04, 0x00]);
+        data.extend_from_slice(&delay.to_le_bytes());
+        data.extend_from_slice(&[0x00, 0x00]);
+        // Image descriptor covering the whole canvas, using the global palette.
+        data.push(0x2c);
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&self.width.to_le_bytes());
+        data.extend_from_slice(&self.height.to_le_bytes());
+        data.push(0x00);
+
+        let indices = frame
+            .pixels()
+            .chunks_exact(4)
+            .map(|pixel| palette_index(pixel[0], pixel[1], pixel[2]))
+            .collect::<Vec<u8>>();
+        data.push(8);
+        for block in lzw_compress(&indices).chunks(255) {
+            data.push(block.len() as u8);
+            data.extend_from_slice(block);
+        }
+        data.push(0x00);
+
+        self.writer.write_all(&data).map_err(|e| e.to_string())
+    }
+
+    /// Writes the trailer and hands back the writer.
+    pub fn finish(mut self) -> Result<W, String> {
+        self.writer.write_all(&[0x3b]).map_err(|e| e.to_string())?;
+        self.writer.flush().map_err(|e| e.to_string())?;
+        Ok(self.writer)
+    }
+}
+
+fn palette_color(index: u8) -> (u8, u8, u8) {
+    let r = ((index >> 5) & 0b111) as u16;
+    let g = ((index >> 2) & 0b111) as u16;
+    let b = (index & 0b11) as u16;
+    (
+        (r * 255 / 7) as u8,
+        (g * 255 / 7) as u8,
+        (b * 255 / 3) as u8,
+    )
+}
+
+fn palette_index(r: u8, g: u8, b: u8) -> u8 {
+    let r = (r as u16 * 7 + 127) / 255;
+    let g = (g as u16 * 7 + 127) / 255;
+    let b = (b as u16 * 3 + 127) / 255;
+    ((r << 5) | (g << 2) | b) as u8
+}
+
+/// Variable code length LZW as used by GIF, for 8 bit indices.
+fn lzw_compress(indices: &[u8]) -> Vec<u8> {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+    const MAX_CODE: u16 = 4095;
+
+    struct BitWriter {
+        bytes: Vec<u8>,
+        buffer: u32,
+        bits: u32,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, code: u16, size: u32) {
+            self.buffer |= (code as u32) << self.bits;
+            self.bits += size;
+            while self.bits >= 8 {
+                self.bytes.push(self.buffer as u8);
+                self.buffer >>= 8;
+                self.bits -= 8;
+            }
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            if self.bits > 0 {
+                self.bytes.push(self.buffer as u8);
+            }
+            self.bytes
+        }
+    }
+
+    let mut out = BitWriter {
+        bytes: Vec::new(),
+        buffer: 0,
+        bits: 0,
+    };
+    // The code for a known string followed by one more index is stored at
+    // `string_code * 256 + index`, 0 meaning there is none yet.
+    let mut dictionary = vec![0u16; (MAX_CODE as usize + 1) * 256];
+    let mut used_entries = Vec::new();
+    let mut next_code = END + 1;
+    let mut code_size = 9;
+
+    out.write(CLEAR, code_size);
+    let mut current = match indices.first() {
+        Some(index) => *index as u16,
+        None => {
+            out.write(END, code_size);
+            return out.finish();
+        }
+    };
+
+    for index in &indices[1..] {
+        let entry = current as usize * 256 + *index as usize;
+        if dictionary[entry] != 0 {
+            current = dictionary[entry];
+            continue;
+        }
+
+        out.write(current, code_size);
+        if next_code <= MAX_CODE {
+            dictionary[entry] = next_code;
+            used_entries.push(entry);
+            // The decoder adds its entries one code later, so it only needs
+            // the wider codes once the first code that does not fit the
+            // current size has been handed out.
+            if next_code == 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+            next_code += 1;
+        } else {
+            out.write(CLEAR, code_size);
+            for entry in used_entries.drain(..) {
+                dictionary[entry] = 0;
+            }
+            next_code = END + 1;
+            code_size = 9;
+        }
+        current = *index as u16;
+    }
+
+    out.write(current, code_size);
+    out.write(END, code_size);
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Splits LZW output back into codes of the sizes a decoder would read
+    /// them at, and decodes them. Returns the codes and the indices.
+    fn lzw_decompress(bytes: &[u8]) -> (Vec<u16>, Vec<u8>) {
+        let mut codes = Vec::new();
+        let mut indices = Vec::new();
+        let mut table = Vec::<Vec<u8>>::new();
+        let mut previous: Option<Vec<u8>> = None;
+        let (mut position, mut code_size) = (0, 9);
+        while position + code_size <= bytes.len() * 8 {
+            let code = (0..code_size)
+                .map(|bit| ((bytes[(position + bit) / 8] >> ((position + bit) % 8)) & 1) as u16)
+                .enumerate()
+                .fold(0, |code, (bit, value)| code | value << bit);
+            position += code_size;
+            codes.push(code);
+
+            match code {
+                256 => {
+                    table = (0..=255).map(|index| vec![index]).collect();
+                    table.extend([Vec::new(), Vec::new()]);
+                    previous = None;
+                    code_size = 9;
+                    continue;
+                }
+                257 => break,
+                _ => {}
+            }
+            let entry = match table.get(code as usize) {
+                Some(entry) => entry.clone(),
+                // The string the encoder added right before writing it.
+                None => {
+                    let previous = previous.clone().unwrap();
+                    [&previous[..], &previous[..1]].concat()
+                }
+            };
+            if let Some(previous) = previous {
+                if table.len() < 4096 {
+                    table.push([&previous[..], &entry[..1]].concat());
+                }
+            }
+            indices.extend_from_slice(&entry);
+            previous = Some(entry);
+            if table.len() == 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+        }
+        (codes, indices)
+    }
+
+    #[test]
+    fn repeated_indices_reuse_codes() {
+        let (codes, indices) = lzw_decompress(&lzw_compress(&[7, 7, 7, 7]));
+        assert_eq!(codes, vec![256, 7, 258, 7, 257]);
+        assert_eq!(indices, vec![7, 7, 7, 7]);
+    }
+
+    #[test]
+    fn codes_grow_to_twelve_bits_and_clear_when_full() {
+        // Noisy enough to fill the dictionary several times over.
+        let mut state = 1u32;
+        let indices = (0..40_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect::<Vec<u8>>();
+
+        let (codes, decoded) = lzw_decompress(&lzw_compress(&indices));
+        assert_eq!(decoded, indices);
+        assert!(codes.iter().any(|code| *code >= 2048));
+        assert!(codes[1..].contains(&256));
+        assert_eq!(codes.last(), Some(&257));
+    }
+}
@@ -0,0 +1,247 @@
+use crate::geometry::Color;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// What the game reports about the current frame for the debug HUD.
+pub struct DebugStats {
+    pub player_position: (f32, f32),
+    /// In degrees.
+    pub player_rotation: f32,
+    /// Column and row of the map cell the player is in.
+    pub player_cell: (u32, u32),
+    pub ray_count: usize,
+    /// Time spent casting the rays of this frame.
+    pub cast_time: Duration,
+    /// Time spent drawing this frame, up to the HUD itself.
+    pub draw_time: Duration,
+}
+
+/// An overlay with the frame rate, a graph of recent frame times and the
+/// state of the player.
+pub struct DebugHud {
+    visible: bool,
+    /// The most recent frame times, oldest first.
+    frame_times: VecDeque<Duration>,
+}
+
+impl DebugHud {
+    /// How many frames the graph and the average frame rate cover.
+    pub const HISTORY: usize = 120;
+    /// Frame time drawn at the full height of the graph.
+    pub(crate) const GRAPH_MAX: Duration = Duration::from_micros(33_333);
+    /// Frame time marked on the graph, frames taking longer are drawn in a
+    /// different color.
+    pub(crate) const GRAPH_BUDGET: Duration = Duration::from_micros(16_667);
+    pub(crate) const GRAPH_HEIGHT: u32 = 30;
+    pub(crate) const TEXT_COLOR: Color = Color::WHITE;
+    pub(crate) const FAST_COLOR: Color = Color::rgb(80, 220, 80);
+    pub(crate) const SLOW_COLOR: Color = Color::rgb(230, 60, 60);
+    pub(crate) const BUDGET_COLOR: Color = Color::rgb(120, 120, 120);
+
+    pub fn new() -> DebugHud {
+        DebugHud {
+            visible: false,
+            frame_times: VecDeque::with_capacity(DebugHud::HISTORY),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Adds the time the last frame took, from the start of one frame to the
+    /// start of the next.
+    pub fn record_frame(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == DebugHud::HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    pub fn frame_times(&self) -> &VecDeque<Duration> {
+        &self.frame_times
+    }
+
+    /// Average frames per second over the recorded history.
+    pub fn fps(&self) -> f32 {
+        let total = self.frame_times.iter().sum::<Duration>().as_secs_f32();
+        if total == 0.0 {
+            return 0.0;
+        }
+        self.frame_times.len() as f32 / total
+    }
+
+    pub(crate) fn text(&self, stats: &DebugStats) -> String {
+        let frame_time = self.frame_times.back().copied().unwrap_or_default();
+        let (x, y) = stats.player_position;
+        let (column, row) = stats.player_cell;
+        format!(
+            "FPS {:.0} ({:.1} ms)\nPOS {x:.1} {y:.1}\nROT {:.1}\nCELL {column} {row}\nRAYS {}\nCAST {:.2} ms\nDRAW {:.2} ms",
+            self.fps(),
+            milliseconds(frame_time),
+            stats.player_rotation,
+            stats.ray_count,
+            milliseconds(stats.cast_time),
+            milliseconds(stats.draw_time),
+        )
+    }
+}
+
+impl Default for DebugHud {
+    fn default() -> DebugHud {
+        DebugHud::new()
+    }
+}
+
+/// What the player has, for the status bar under the 3D view.
+pub struct Status {
+    pub health: f32,
+    pub armor: f32,
+    pub ammo: u32,
+    pub score: u32,
+    /// Ids of the keys the player carries.
+    pub keys: Vec<u8>,
+}
+
+impl Status {
+    pub(crate) const HEIGHT: u32 = 30;
+    pub(crate) const TEXT_SCALE: u32 = 2;
+    pub(crate) const TEXT_COLOR: Color = Color::WHITE;
+    /// Keys are shown as squares in these colors, by id.
+    pub(crate) const KEY_COLORS: [Color; 4] = [
+        Color::rgb(220, 40, 40),
+        Color::rgb(40, 90, 230),
+        Color::rgb(240, 210, 40),
+        Color::rgb(40, 190, 70),
+    ];
+
+    pub(crate) fn text(&self) -> String {
+        format!(
+            "HEALTH {:.0}  ARMOR {:.0}  AMMO {}  SCORE {}",
+            self.health.ceil(),
+            self.armor.ceil(),
+            self.ammo,
+            self.score
+        )
+    }
+}
+
+/// A red border around the 3D view when the player gets hurt, fading out
+/// over `FADE_TIME`.
+pub struct DamageFlash {
+    /// `1.0` right after a heavy hit, `0.0` once faded out.
+    strength: f32,
+}
+
+impl DamageFlash {
+    /// How long a flash at full strength takes to fade out.
+    pub const FADE_TIME: Duration = Duration::from_millis(500);
+    /// Damage that makes a flash at full strength.
+    pub const FULL_DAMAGE: f32 = 25.0;
+    /// Width of the border at full strength, in pixels.
+    pub(crate) const MAX_WIDTH: u32 = 24;
+    pub(crate) const COLOR: Color = Color::rgb(200, 20, 20);
+
+    pub fn new() -> DamageFlash {
+        DamageFlash { strength: 0.0 }
+    }
+
+    pub fn strength(&self) -> f32 {
+        self.strength
+    }
+
+    /// Starts a flash for `damage`, or makes the current one stronger.
+    pub fn hit(&mut self, damage: f32) {
+        let strength = (damage / DamageFlash::FULL_DAMAGE).clamp(0.0, 1.0);
+        self.strength = (self.strength + strength).min(1.0);
+    }
+
+    /// Fades the flash by the time the last frame took.
+    pub fn fade(&mut self, frame_time: Duration) {
+        let faded = frame_time.as_secs_f32() / DamageFlash::FADE_TIME.as_secs_f32();
+        self.strength = (self.strength - faded).max(0.0);
+    }
+}
+
+impl Default for DamageFlash {
+    fn default() -> DamageFlash {
+        DamageFlash::new()
+    }
+}
+
+/// What a shot hit, for the spark it makes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Impact {
+    Nothing,
+    Wall,
+    Entity,
+}
+
+/// What the player's last shot leaves on screen for a moment: a muzzle flash
+/// at the bottom of the 3D view and a spark in its middle where the shot
+/// landed. The maps draw a tracer to `point`, see `PlayerView::shot`.
+pub struct Gunfire {
+    /// Where the shot landed, in world units, and what it hit.
+    shot: Option<((f32, f32), Impact)>,
+    /// `1.0` right after a shot, `0.0` once faded out.
+    strength: f32,
+}
+
+impl Gunfire {
+    pub const FADE_TIME: Duration = Duration::from_millis(150);
+    /// Size of the muzzle flash at full strength, in pixels.
+    pub(crate) const MUZZLE_SIZE: (u32, u32) = (40, 30);
+    pub(crate) const SPARK_SIZE: u32 = 10;
+    pub(crate) const MUZZLE_COLOR: Color = Color::rgb(255, 220, 120);
+    pub(crate) const WALL_SPARK_COLOR: Color = Color::rgb(255, 255, 200);
+    pub(crate) const ENTITY_SPARK_COLOR: Color = Color::rgb(220, 30, 30);
+
+    pub fn new() -> Gunfire {
+        Gunfire {
+            shot: None,
+            strength: 0.0,
+        }
+    }
+
+    pub fn strength(&self) -> f32 {
+        self.strength
+    }
+    pub fn impact(&self) -> Option<Impact> {
+        self.shot.map(|(_, impact)| impact)
+    }
+    /// Where the last shot landed, while it is still shown.
+    pub fn point(&self) -> Option<(f32, f32)> {
+        self.shot.map(|(point, _)| point)
+    }
+
+    /// Shows a shot that landed at `point`, replacing the previous one.
+    pub fn fire(&mut self, point: (f32, f32), impact: Impact) {
+        self.shot = Some((point, impact));
+        self.strength = 1.0;
+    }
+
+    /// Fades the shot by the time the last frame took.
+    pub fn fade(&mut self, frame_time: Duration) {
+        let faded = frame_time.as_secs_f32() / Gunfire::FADE_TIME.as_secs_f32();
+        self.strength = (self.strength - faded).max(0.0);
+        if self.strength == 0.0 {
+            self.shot = None;
+        }
+    }
+}
+
+impl Default for Gunfire {
+    fn default() -> Gunfire {
+        Gunfire::new()
+    }
+}
+
+fn milliseconds(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
@@ -0,0 +1,157 @@
+use crate::framebuffer::Framebuffer;
+use crate::gif::GifEncoder;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Bmp,
+    Ppm,
+    Gif,
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "bmp" => Some(ImageFormat::Bmp),
+            "ppm" => Some(ImageFormat::Ppm),
+            "gif" => Some(ImageFormat::Gif),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Gif => "gif",
+        }
+    }
+}
+
+pub fn write_image(frame: &Framebuffer, path: &Path, format: ImageFormat) -> Result<(), String> {
+    let data = match format {
+        ImageFormat::Bmp => encode_bmp(frame),
+        ImageFormat::Ppm => encode_ppm(frame),
+        ImageFormat::Gif => {
+            let mut encoder = GifEncoder::new(Vec::new(), frame.width(), frame.height(), false)?;
+            encoder.add_frame(frame, 0)?;
+            encoder.finish()?
+        }
+    };
+    fs::write(path, data).map_err(|e| format!("Could not write {}: {e}", path.display()))
+}
+
+/// Binary PPM, dropping the alpha channel.
+fn encode_ppm(frame: &Framebuffer) -> Vec<u8> {
+    let mut data = format!("P6\n{} {}\n255\n", frame.width(), frame.height()).into_bytes();
+    for pixel in frame.pixels().chunks_exact(4) {
+        data.extend_from_slice(&pixel[..3]);
+    }
+    data
+}
+
+/// Uncompressed 24 bit BMP. Rows are stored bottom up in BGR order, each one
+/// padded to a multiple of four bytes.
+fn encode_bmp(frame: &Framebuffer) -> Vec<u8> {
+    const HEADER_SIZE: u32 = 14 + 40;
+
+    let (width, height) = (frame.width(), frame.height());
+    let row_size = (width * 3).div_ceil(4) * 4;
+    let image_size = row_size * height;
+
+    let mut data = Vec::with_capacity((HEADER_SIZE + image_size) as usize);
+    // File header.
+    data.extend_from_slice(b"BM");
+    data.extend_from_slice(&(HEADER_SIZE + image_size).to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+    // BITMAPINFOHEADER.
+    data.extend_from_slice(&40u32.to_le_bytes());
+    data.extend_from_slice(&(width as i32).to_le_bytes());
+    data.extend_from_slice(&(height as i32).to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&24u16.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&image_size.to_le_bytes());
+    data.extend_from_slice(&2835i32.to_le_bytes());
+    data.extend_from_slice(&2835i32.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+
+    let pixels = frame.pixels();
+    for y in (0..height).rev() {
+        let row_start = data.len();
+        for x in 0..width {
+            let i = ((y * width + x) * 4) as usize;
+            data.extend_from_slice(&[pixels[i + 2], pixels[i + 1], pixels[i]]);
+        }
+        data.resize(row_start + row_size as usize, 0);
+    }
+    data
+}
+
+/// A file name like `screenshot-2024-05-01_13-37-00-042.bmp`, using UTC time.
+pub fn timestamped_file_name(prefix: &str, format: ImageFormat) -> PathBuf {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    let time = seconds % 86400;
+
+    PathBuf::from(format!(
+        "{prefix}-{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}-{:03}.{}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        since_epoch.subsec_millis(),
+        format.extension()
+    ))
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date, using Howard
+/// Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bmp_rows_are_padded_and_bottom_up() {
+        // Pixel n is colored 3n + 1, 3n + 2, 3n + 3.
+        let pixels = (0..6u8)
+            .flat_map(|pixel| [pixel * 3 + 1, pixel * 3 + 2, pixel * 3 + 3, 255])
+            .collect();
+        let frame = Framebuffer::from_pixels(3, 2, pixels).unwrap();
+
+        let mut expected = vec![b'B', b'M', 78, 0, 0, 0, 0, 0, 0, 0, 54, 0, 0, 0];
+        expected.extend_from_slice(&[40, 0, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0, 1, 0, 24, 0]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 24, 0, 0, 0]);
+        expected.extend_from_slice(&[0x13, 0x0b, 0, 0, 0x13, 0x0b, 0, 0]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+        // The bottom row first, in BGR order, padded from 9 to 12 bytes.
+        expected.extend_from_slice(&[12, 11, 10, 15, 14, 13, 18, 17, 16, 0, 0, 0]);
+        expected.extend_from_slice(&[3, 2, 1, 6, 5, 4, 9, 8, 7, 0, 0, 0]);
+        assert_eq!(encode_bmp(&frame), expected);
+    }
+}
@@ -1,189 +1,542 @@
-use sdl2::pixels;
+mod automap;
+mod backend;
+mod console;
+mod error;
+mod font;
+mod framebuffer;
+mod geometry;
+mod gif;
+mod hud;
+mod image;
+mod minimap;
+mod recorder;
+mod sdl_backend;
+mod terminal;
+mod theme;
+
+pub use crate::automap::Automap;
+pub use crate::backend::Backend;
+pub use crate::console::Console;
+pub use crate::error::RendererError;
+pub use crate::font::{text_size, GLYPH_HEIGHT, GLYPH_WIDTH, LINE_HEIGHT};
+pub use crate::framebuffer::Framebuffer;
+pub use crate::geometry::{Color, Point, Rect};
+pub use crate::gif::GifEncoder;
+pub use crate::hud::{DamageFlash, DebugHud, DebugStats, Gunfire, Impact, Status};
+pub use crate::image::{timestamped_file_name, ImageFormat};
+pub use crate::minimap::{EntityKind, EntityMarker, MapView, Minimap, PlayerMarker, PlayerView};
+pub use crate::recorder::FrameRecorder;
+pub use crate::sdl_backend::SdlBackend;
+pub use crate::terminal::{TerminalBackend, TerminalInput, TerminalKey};
+pub use crate::theme::Theme;
+
+use crate::minimap::MinimapColors;
+use std::path::{Path, PathBuf};
 
-#[allow(dead_code)]
 pub struct Renderer {
-    sdl_context: sdl2::Sdl,
-    video_subsystem: sdl2::VideoSubsystem,
-    canvas: sdl2::render::Canvas<sdl2::video::Window>,
-
-    background_color: sdl2::pixels::Color,
-    wall_color: sdl2::pixels::Color,
-    floor_color: sdl2::pixels::Color,
-    player_color: sdl2::pixels::Color,
-    ray_color: sdl2::pixels::Color,
-    sky_color: sdl2::pixels::Color,
+    backend: Box<dyn Backend>,
+    /// Only set when rendering to an SDL window, needed for the event pump.
+    sdl_context: Option<sdl2::Sdl>,
+    scale: f32,
+    /// Draws the 3D view into `view_frame` and uploads it in one go, instead of
+    /// drawing every column with its own draw calls.
+    software_rendering: bool,
+    view_frame: Framebuffer,
+    minimap: Minimap,
+    minimap_frame: Framebuffer,
+    automap: Automap,
+    automap_frame: Framebuffer,
+    debug_hud: DebugHud,
+    damage_flash: DamageFlash,
+    gunfire: Gunfire,
+    console: Console,
+    theme: Theme,
 }
 
 impl Renderer {
+    /// Opens a window, panicking if that fails. See `try_new`.
     pub fn new(width: u32, height: u32, title: &str) -> Renderer {
-        let sdl_context = sdl2::init().unwrap();
-        let video_subsystem = sdl_context.video().unwrap();
-        let window = video_subsystem
-            .window(title, width, height)
-            .position_centered()
-            .build()
-            .unwrap();
-        let canvas = window.into_canvas().build().unwrap();
-
-        let background_color = sdl2::pixels::Color::RGB(0, 0, 0);
-        let wall_color = sdl2::pixels::Color::RGB(0, 0, 0);
-        let floor_color = sdl2::pixels::Color::RGB(0, 0, 0);
-        let player_color = sdl2::pixels::Color::RGB(0, 0, 0);
-        let ray_color = sdl2::pixels::Color::RGB(0, 0, 0);
-        let sky_color = sdl2::pixels::Color::RGB(0, 0, 0);
+        match Renderer::try_new(width, height, title) {
+            Ok(renderer) => renderer,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// Opens a window to render into.
+    pub fn try_new(width: u32, height: u32, title: &str) -> Result<Renderer, RendererError> {
+        let sdl_context = sdl2::init().map_err(RendererError::Init)?;
+        let backend =
+            SdlBackend::new(&sdl_context, width, height, title).map_err(RendererError::Init)?;
+
+        let mut renderer = Renderer::with_backend(Box::new(backend));
+        renderer.sdl_context = Some(sdl_context);
+        Ok(renderer)
+    }
+
+    /// Creates a renderer that draws into an in-memory framebuffer instead of a
+    /// window, so it works without a display.
+    pub fn new_headless(width: u32, height: u32) -> Renderer {
+        Renderer::with_backend(Box::new(Framebuffer::new(width, height)))
+    }
 
+    pub fn with_backend(backend: Box<dyn Backend>) -> Renderer {
         Renderer {
-            sdl_context,
-            video_subsystem,
-            canvas,
-            background_color,
-            wall_color,
-            floor_color,
-            player_color,
-            ray_color,
-            sky_color,
+            backend,
+            sdl_context: None,
+            scale: 1.0,
+            software_rendering: false,
+            view_frame: Framebuffer::new(1, 1),
+            minimap: Minimap::new(Rect::new(0, 0, 210, 210), 35.0),
+            minimap_frame: Framebuffer::new(1, 1),
+            automap: Automap::new(),
+            automap_frame: Framebuffer::new(1, 1),
+            debug_hud: DebugHud::new(),
+            damage_flash: DamageFlash::new(),
+            gunfire: Gunfire::new(),
+            console: Console::new(),
+            theme: Theme::default(),
         }
     }
 
-    pub fn set_background_color(&mut self, color: sdl2::pixels::Color) {
-        self.background_color = color;
+    pub fn theme(&self) -> &Theme {
+        &self.theme
     }
-    pub fn set_wall_color(&mut self, color: sdl2::pixels::Color) {
-        self.wall_color = color;
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
     }
-    pub fn set_floor_color(&mut self, color: sdl2::pixels::Color) {
-        self.floor_color = color
+
+    pub fn set_background_color(&mut self, color: Color) {
+        self.theme.background = color;
+    }
+    pub fn set_wall_color(&mut self, color: Color) {
+        self.theme.wall = color;
+    }
+    pub fn set_floor_color(&mut self, color: Color) {
+        self.theme.floor = color
     }
-    pub fn set_player_color(&mut self, color: sdl2::pixels::Color) {
-        self.player_color = color
+    pub fn set_player_color(&mut self, color: Color) {
+        self.theme.player = color
     }
-    pub fn set_ray_color(&mut self, color: sdl2::pixels::Color) {
-        self.ray_color = color
+    pub fn set_ray_color(&mut self, color: Color) {
+        self.theme.ray = color
     }
-    pub fn set_sky_color(&mut self, color: sdl2::pixels::Color) {
-        self.sky_color = color
+    pub fn set_sky_color(&mut self, color: Color) {
+        self.theme.sky = color
     }
-    pub fn set_scale(&mut self, scale: f32) -> Result<(), String> {
-        self.canvas.set_scale(scale, scale)
+    pub fn set_scale(&mut self, scale: f32) -> Result<(), RendererError> {
+        self.backend.set_scale(scale).map_err(RendererError::Draw)?;
+        self.scale = scale;
+        Ok(())
     }
 
-    pub fn present_canvas(&mut self) {
-        self.canvas.present();
+    pub fn set_software_rendering(&mut self, enabled: bool) {
+        self.software_rendering = enabled;
+    }
+    pub fn software_rendering(&self) -> bool {
+        self.software_rendering
     }
 
-    pub fn clear_canvas(&mut self) {
-        self.canvas.set_draw_color(self.background_color);
-        self.canvas.clear();
+    pub fn minimap(&self) -> &Minimap {
+        &self.minimap
+    }
+    pub fn minimap_mut(&mut self) -> &mut Minimap {
+        &mut self.minimap
+    }
+    pub fn automap(&self) -> &Automap {
+        &self.automap
+    }
+    pub fn automap_mut(&mut self) -> &mut Automap {
+        &mut self.automap
+    }
+    pub fn debug_hud(&self) -> &DebugHud {
+        &self.debug_hud
+    }
+    pub fn debug_hud_mut(&mut self) -> &mut DebugHud {
+        &mut self.debug_hud
+    }
+    pub fn damage_flash(&self) -> &DamageFlash {
+        &self.damage_flash
+    }
+    pub fn damage_flash_mut(&mut self) -> &mut DamageFlash {
+        &mut self.damage_flash
+    }
+    pub fn gunfire(&self) -> &Gunfire {
+        &self.gunfire
+    }
+    pub fn gunfire_mut(&mut self) -> &mut Gunfire {
+        &mut self.gunfire
+    }
+    pub fn console(&self) -> &Console {
+        &self.console
+    }
+    pub fn console_mut(&mut self) -> &mut Console {
+        &mut self.console
     }
 
-    pub fn event_pump(&self) -> sdl2::EventPump {
-        self.sdl_context.event_pump().unwrap()
+    /// The framebuffer of a headless renderer, `None` when drawing to a window.
+    pub fn framebuffer(&self) -> Option<&Framebuffer> {
+        self.backend.framebuffer()
     }
 
-    pub fn draw_line(
-        &mut self,
-        start: &sdl2::rect::Point,
-        end: &sdl2::rect::Point,
-    ) -> Result<(), String> {
-        self.canvas.set_draw_color(self.wall_color);
-        self.canvas.draw_line(*start, *end)
+    /// Saves what has been drawn so far. When `path` is a directory the image
+    /// gets a timestamped BMP file name inside it, otherwise the format is
+    /// picked from its extension. Returns the path of the written file.
+    pub fn screenshot(&mut self, path: &Path) -> Result<PathBuf, RendererError> {
+        let path = if path.is_dir() {
+            path.join(image::timestamped_file_name("screenshot", ImageFormat::Bmp))
+        } else {
+            path.to_path_buf()
+        };
+
+        self.read_pixels()?.save(&path)?;
+        Ok(path)
     }
 
-    pub fn draw_square(&mut self, middle: &sdl2::rect::Point, width: u32) -> Result<(), String> {
-        self.canvas.set_draw_color(self.wall_color);
-        self.canvas
-            .fill_rect(sdl2::rect::Rect::new(middle.x, middle.y, width, width))
+    /// Reads back what has been drawn so far, at the output resolution.
+    pub fn read_pixels(&mut self) -> Result<Framebuffer, RendererError> {
+        self.backend.read_pixels().map_err(RendererError::Draw)
     }
 
-    pub fn draw_map(
-        &mut self,
-        map: &[u16],
-        map_width: u8,
-        map_height: u8,
-        block_size: u32,
-    ) -> Result<(), String> {
-        use sdl2::rect::Rect;
-
-        self.canvas.set_draw_color(self.floor_color);
-        let drawing_res = self.canvas.fill_rect(Rect::new(
-            (map_width / 2) as i32 - 1,
-            (map_height / 2) as i32 - 1,
-            map_width as u32 * block_size,
-            map_height as u32 * block_size,
-        ));
-        match drawing_res {
-            Ok(_) => {}
-            Err(_) => return drawing_res,
+    pub fn present_canvas(&mut self) -> Result<(), RendererError> {
+        self.backend.present().map_err(RendererError::Draw)
+    }
+
+    pub fn clear_canvas(&mut self) -> Result<(), RendererError> {
+        self.backend
+            .clear(self.theme.background)
+            .map_err(RendererError::Draw)
+    }
+
+    pub fn event_pump(&self) -> Result<sdl2::EventPump, RendererError> {
+        match &self.sdl_context {
+            Some(sdl_context) => sdl_context.event_pump().map_err(RendererError::Init),
+            None => Err(RendererError::Init(
+                "Renderer has no SDL window to pump events from".to_string(),
+            )),
         }
+    }
 
-        self.canvas.set_draw_color(self.wall_color);
-        let walled_spaces = map
-            .iter()
-            .map(|x| (x % map_width as u16, x / map_width as u16))
-            .map(|(x, y)| {
-                Rect::new(
-                    (x as i32) * (block_size as i32),
-                    (y as i32) * (block_size as i32),
-                    block_size,
-                    block_size,
-                )
-            })
-            .collect::<Vec<Rect>>();
+    pub fn draw_line(&mut self, start: &Point, end: &Point) -> Result<(), RendererError> {
+        self.backend
+            .draw_line(*start, *end, self.theme.wall)
+            .map_err(RendererError::Draw)
+    }
 
-        self.canvas.fill_rects(&walled_spaces)
+    pub fn draw_square(&mut self, middle: &Point, width: u32) -> Result<(), RendererError> {
+        self.backend
+            .fill_rect(Rect::new(middle.x, middle.y, width, width), self.theme.wall)
+            .map_err(RendererError::Draw)
     }
 
-    pub fn draw_player(
+    /// Draws `text` with the built-in font, its top left corner at `position`.
+    /// Each font pixel covers `scale` by `scale` screen pixels, see `text_size`
+    /// for how much room that takes.
+    pub fn draw_text(
         &mut self,
-        position: sdl2::rect::Point,
-        player_rotation: f32,
-    ) -> Result<(), String> {
-        use sdl2::rect::Point;
-        self.canvas.set_draw_color(self.player_color);
-
-        let x = position.x;
-        let y = position.y;
-        let drawing_res = self.canvas.draw_line(
-            Renderer::rotate_point(Point::new(x, y + 3), position, player_rotation),
-            Renderer::rotate_point(Point::new(x, y - 3), position, player_rotation),
-        );
-        match drawing_res {
-            Ok(_) => {}
-            Err(_) => return drawing_res,
-        }
+        text: &str,
+        position: Point,
+        color: Color,
+        scale: u32,
+    ) -> Result<(), RendererError> {
+        let rects = font::text_rects(text, position, scale.max(1));
+        self.backend
+            .fill_rects(&rects, color)
+            .map_err(RendererError::Draw)
+    }
 
-        self.canvas.draw_line(
-            Renderer::rotate_point(Point::new(x - 5, y), position, player_rotation),
-            Renderer::rotate_point(Point::new(x + 2, y), position, player_rotation),
+    /// Draws the map, the player and their rays into the minimap's frame.
+    pub fn draw_minimap(
+        &mut self,
+        map: &MapView,
+        player: &PlayerView,
+    ) -> Result<(), RendererError> {
+        let colors = self.map_colors();
+        Renderer::draw_map_widget(
+            self.backend.as_mut(),
+            &mut self.minimap_frame,
+            self.scale,
+            &self.minimap,
+            map,
+            player,
+            &colors,
         )
     }
 
-    pub fn draw_rays(
+    /// Draws the automap over the whole screen, if it is visible.
+    pub fn draw_automap(
         &mut self,
-        player_position: sdl2::rect::Point,
-        ray_targets: Vec<sdl2::rect::Point>,
-    ) -> Result<(), String> {
-        self.canvas.set_draw_color(self.ray_color);
+        map: &MapView,
+        player: &PlayerView,
+    ) -> Result<(), RendererError> {
+        if !self.automap.is_visible() {
+            return Ok(());
+        }
 
-        for ray_target in ray_targets {
-            match self.canvas.draw_line(player_position, ray_target) {
-                Ok(_) => {}
-                Err(s) => return Err(s),
+        let (width, height) = self.backend.size();
+        let frame = Rect::new(
+            0,
+            0,
+            (width as f32 / self.scale) as u32,
+            (height as f32 / self.scale) as u32,
+        );
+        let colors = self.map_colors();
+        Renderer::draw_map_widget(
+            self.backend.as_mut(),
+            &mut self.automap_frame,
+            self.scale,
+            self.automap.view(frame),
+            map,
+            player,
+            &colors,
+        )
+    }
+
+    /// Draws the border of the damage flash around the 3D view, if there is
+    /// one.
+    pub fn draw_damage_flash(&mut self) -> Result<(), RendererError> {
+        let width = (DamageFlash::MAX_WIDTH as f32 * self.damage_flash.strength()).round() as u32;
+        if width == 0 {
+            return Ok(());
+        }
+
+        let view = Rect::new(
+            Renderer::VIEW_X,
+            0,
+            Renderer::VIEW_WIDTH,
+            Renderer::VIEW_HEIGHT,
+        );
+        let border = [
+            Rect::new(view.x, view.y, view.width, width),
+            Rect::new(view.x, view.bottom() - width as i32, view.width, width),
+            Rect::new(view.x, view.y, width, view.height),
+            Rect::new(view.right() - width as i32, view.y, width, view.height),
+        ];
+        self.backend
+            .fill_rects(&border, DamageFlash::COLOR)
+            .map_err(RendererError::Draw)
+    }
+
+    /// Draws the muzzle flash of the last shot at the bottom of the 3D view
+    /// and a spark in its middle if the shot hit something, while the shot
+    /// is shown.
+    pub fn draw_gunfire(&mut self) -> Result<(), RendererError> {
+        let strength = self.gunfire.strength();
+        let Some(impact) = self.gunfire.impact() else {
+            return Ok(());
+        };
+
+        let center_x = Renderer::VIEW_X + Renderer::VIEW_WIDTH as i32 / 2;
+        let (muzzle_width, muzzle_height) = Gunfire::MUZZLE_SIZE;
+        let (width, height) = (
+            (muzzle_width as f32 * strength) as u32,
+            (muzzle_height as f32 * strength) as u32,
+        );
+        let muzzle = Rect::new(
+            center_x - width as i32 / 2,
+            (Renderer::VIEW_HEIGHT - height) as i32,
+            width,
+            height,
+        );
+        self.backend
+            .fill_rect(muzzle, Gunfire::MUZZLE_COLOR)
+            .map_err(RendererError::Draw)?;
+
+        let color = match impact {
+            Impact::Nothing => return Ok(()),
+            Impact::Wall => Gunfire::WALL_SPARK_COLOR,
+            Impact::Entity => Gunfire::ENTITY_SPARK_COLOR,
+        };
+        let size = (Gunfire::SPARK_SIZE as f32 * strength) as u32;
+        let spark = Rect::new(
+            center_x - size as i32 / 2,
+            (Renderer::VIEW_HEIGHT - size) as i32 / 2,
+            size,
+            size,
+        );
+        self.backend
+            .fill_rect(spark, color)
+            .map_err(RendererError::Draw)
+    }
+
+    /// Draws the player's health, armor, ammo, score and keys in a bar under
+    /// the 3D view.
+    pub fn draw_status(&mut self, status: &Status) -> Result<(), RendererError> {
+        let bar = Rect::new(
+            Renderer::VIEW_X,
+            Renderer::VIEW_HEIGHT as i32,
+            Renderer::VIEW_WIDTH,
+            Status::HEIGHT,
+        );
+        self.backend
+            .fill_rect(bar, self.theme.background)
+            .map_err(RendererError::Draw)?;
+
+        let text = status.text();
+        let (_, text_height) = text_size(&text, Status::TEXT_SCALE);
+        let padding = (Status::HEIGHT - text_height) as i32 / 2;
+        let top = bar.y + padding;
+        self.draw_text(
+            &text,
+            Point::new(bar.x + padding, top),
+            Status::TEXT_COLOR,
+            Status::TEXT_SCALE,
+        )?;
+
+        // Keys go to the right end of the bar, out of the way of the text.
+        let key_step = (text_height + padding as u32) as i32;
+        let mut left = bar.right() - key_step * status.keys.len() as i32;
+        for key in &status.keys {
+            let color = Status::KEY_COLORS[*key as usize % Status::KEY_COLORS.len()];
+            self.backend
+                .fill_rect(Rect::new(left, top, text_height, text_height), color)
+                .map_err(RendererError::Draw)?;
+            left += key_step;
+        }
+        Ok(())
+    }
+
+    /// Draws the debug HUD in the top left corner of the 3D view, if it is
+    /// visible.
+    pub fn draw_debug_hud(&mut self, stats: &DebugStats) -> Result<(), RendererError> {
+        if !self.debug_hud.is_visible() {
+            return Ok(());
+        }
+
+        const PADDING: u32 = 4;
+        let text = self.debug_hud.text(stats);
+        let (text_width, text_height) = text_size(&text, 1);
+        let graph_width = DebugHud::HISTORY as u32;
+        let panel = Rect::new(
+            Renderer::VIEW_X + PADDING as i32,
+            PADDING as i32,
+            text_width.max(graph_width) + 2 * PADDING,
+            text_height + DebugHud::GRAPH_HEIGHT + 3 * PADDING,
+        );
+        self.backend
+            .fill_rect(panel, self.theme.background)
+            .map_err(RendererError::Draw)?;
+
+        let left = panel.x + PADDING as i32;
+        self.draw_text(
+            &text,
+            Point::new(left, panel.y + PADDING as i32),
+            DebugHud::TEXT_COLOR,
+            1,
+        )?;
+
+        let graph_bottom = panel.bottom() - PADDING as i32;
+        let bar_height = |time: std::time::Duration| {
+            let share = time.as_secs_f32() / DebugHud::GRAPH_MAX.as_secs_f32();
+            (share.min(1.0) * DebugHud::GRAPH_HEIGHT as f32).round() as u32
+        };
+        for (i, time) in self.debug_hud.frame_times().iter().enumerate() {
+            let height = bar_height(*time).max(1);
+            let color = if *time > DebugHud::GRAPH_BUDGET {
+                DebugHud::SLOW_COLOR
+            } else {
+                DebugHud::FAST_COLOR
             };
+            self.backend
+                .fill_rect(
+                    Rect::new(left + i as i32, graph_bottom - height as i32, 1, height),
+                    color,
+                )
+                .map_err(RendererError::Draw)?;
         }
+        let budget_y = graph_bottom - bar_height(DebugHud::GRAPH_BUDGET) as i32;
+        self.backend
+            .draw_line(
+                Point::new(left, budget_y),
+                Point::new(left + graph_width as i32 - 1, budget_y),
+                DebugHud::BUDGET_COLOR,
+            )
+            .map_err(RendererError::Draw)
+    }
 
-        Result::Ok(())
+    /// Draws the console over the top of the screen, if it is open.
+    pub fn draw_console(&mut self) -> Result<(), RendererError> {
+        if !self.console.is_open() {
+            return Ok(());
+        }
+
+        const PADDING: i32 = 4;
+        let line_height = LINE_HEIGHT as i32;
+        let (width, _) = self.backend.size();
+        let lines = Console::VISIBLE_LINES as i32;
+        let panel = Rect::new(
+            0,
+            0,
+            (width as f32 / self.scale) as u32,
+            ((lines + 1) * line_height + 2 * PADDING) as u32,
+        );
+        self.backend
+            .fill_rect(panel, Console::BACKGROUND_COLOR)
+            .map_err(RendererError::Draw)?;
+
+        let log = self.console.log();
+        let shown = log.len().min(Console::VISIBLE_LINES);
+        let text = log
+            .iter()
+            .skip(log.len() - shown)
+            .cloned()
+            .collect::<Vec<String>>()
+            .join("\n");
+        let log_top = PADDING + (lines - shown as i32) * line_height;
+        self.draw_text(&text, Point::new(PADDING, log_top), Console::TEXT_COLOR, 1)?;
+
+        let input = format!("> {}_", self.console.input());
+        self.draw_text(
+            &input,
+            Point::new(PADDING, PADDING + lines * line_height),
+            Console::INPUT_COLOR,
+            1,
+        )
     }
-    pub fn draw_screen(
-        &mut self,
-        topleft: sdl2::rect::Point,
-        botright: sdl2::rect::Point,
-    ) -> Result<(), String> {
-        use sdl2::rect::Point;
-        use sdl2::rect::Rect;
 
-        self.canvas.set_draw_color(self.floor_color);
+    fn map_colors(&self) -> MinimapColors {
+        MinimapColors {
+            background: self.theme.background,
+            wall: self.theme.wall,
+            floor: self.theme.floor,
+            closed_door: self.theme.wall_color(Theme::DOOR_MATERIAL),
+            // Open doors are drawn halfway between a door and the floor.
+            open_door: Renderer::blend(
+                self.theme.wall_color(Theme::DOOR_MATERIAL),
+                self.theme.floor,
+            ),
+            grid: Renderer::shade(self.theme.floor, 0.7),
+            player: self.theme.player,
+            ray: self.theme.ray,
+            tracer: Gunfire::MUZZLE_COLOR,
+            enemy: EntityMarker::ENEMY_COLOR,
+            pickup: EntityMarker::PICKUP_COLOR,
+            prop: EntityMarker::PROP_COLOR,
+        }
+    }
+
+    fn draw_map_widget(
+        backend: &mut dyn Backend,
+        target: &mut Framebuffer,
+        scale: f32,
+        widget: &Minimap,
+        map: &MapView,
+        player: &PlayerView,
+        colors: &MinimapColors,
+    ) -> Result<(), RendererError> {
+        let frame = widget.frame();
+        let width = (frame.width as f32 * scale) as u32;
+        let height = (frame.height as f32 * scale) as u32;
+        if target.width() != width || target.height() != height {
+            *target = Framebuffer::new(width, height);
+        }
+
+        widget.rasterize(target, scale, map, player, colors);
+        backend
+            .blit_frame(target, frame)
+            .map_err(RendererError::Draw)
+    }
 
+    pub fn draw_screen(&mut self, topleft: Point, botright: Point) -> Result<(), RendererError> {
         let diff = Point::new(
             (botright.x - topleft.x).abs(),
             (topleft.y - botright.y).abs(),
@@ -191,32 +544,40 @@ impl Renderer {
 
         let rect = Rect::new(topleft.x, topleft.y, diff.x as u32, diff.y as u32);
 
-        self.canvas.fill_rect(rect)
+        self.backend
+            .fill_rect(rect, self.theme.floor)
+            .map_err(RendererError::Draw)
     }
 
-    pub fn draw_walls(&mut self, rays: Vec<(f32, bool)>) -> Result<(), String> {
-        // println!("\n\n");
-        use sdl2::rect::Rect;
+    const VIEW_X: i32 = 250;
+    const VIEW_WIDTH: u32 = 800 - 250;
+    const VIEW_HEIGHT: u32 = 300;
 
-        const WIDTH: u32 = 800 - 250;
-        const HEIGHT: u32 = 300;
+    /// Draws the 3D view, one column per ray. Each ray is its closeness, `1.0`
+    /// being right in front of the player, whether it hit a wall and the
+    /// material of that wall.
+    pub fn draw_walls(&mut self, rays: Vec<(f32, bool, u8)>) -> Result<(), RendererError> {
+        if self.software_rendering {
+            return self.rasterize_walls(&rays);
+        }
 
-        self.canvas.set_draw_color(self.wall_color);
+        const WIDTH: u32 = Renderer::VIEW_WIDTH;
+        const HEIGHT: u32 = Renderer::VIEW_HEIGHT;
 
         let ray_count = rays.iter().len();
         let column_width = (WIDTH as f32) / ray_count as f32;
         let rects = rays
             .iter()
             .enumerate()
-            .flat_map(|(i, (r, b))| {
-                let x = 250.0 + (WIDTH as f32) / ray_count as f32 * i as f32;
+            .flat_map(|(i, (r, b, material))| {
+                let x = Renderer::VIEW_X as f32 + (WIDTH as f32) / ray_count as f32 * i as f32;
                 let column_height = (HEIGHT as f32) * r;
 
                 let y = (HEIGHT as f32 - column_height) / 2.0;
 
                 let sky = (
                     Rect::new(x as i32, 0, column_width as u32, y as u32),
-                    self.sky_color,
+                    self.theme.sky,
                 );
 
                 if !(*b) {
@@ -230,16 +591,17 @@ impl Renderer {
                         column_width as u32,
                         column_height as u32,
                     ),
-                    self.wall_color,
+                    self.theme.wall_color(*material),
                 );
                 vec![sky, wall]
             })
-            .collect::<Vec<(Rect, pixels::Color)>>();
+            .collect::<Vec<(Rect, Color)>>();
 
-        // self.canvas.fill_rects(&rects)
         for (rect, col) in rects {
-            self.canvas.set_draw_color(col);
-            let draw_res = self.canvas.fill_rect(rect);
+            let draw_res = self
+                .backend
+                .fill_rect(rect, col)
+                .map_err(RendererError::Draw);
             match draw_res {
                 Ok(_) => {}
                 Err(_) => return draw_res,
@@ -249,25 +611,148 @@ impl Renderer {
         Result::Ok(())
     }
 
-    fn rotate_point(
-        p: sdl2::rect::Point,
-        center: sdl2::rect::Point,
-        rotation: f32,
-    ) -> sdl2::rect::Point {
-        use sdl2::rect::Point;
+    /// Software version of `draw_walls`, drawing sky, walls and floor pixel by
+    /// pixel at the output resolution and uploading the result as one frame.
+    fn rasterize_walls(&mut self, rays: &[(f32, bool, u8)]) -> Result<(), RendererError> {
+        let width = (Renderer::VIEW_WIDTH as f32 * self.scale) as u32;
+        let height = (Renderer::VIEW_HEIGHT as f32 * self.scale) as u32;
+        if self.view_frame.width() != width || self.view_frame.height() != height {
+            self.view_frame = Framebuffer::new(width, height);
+        }
+        if rays.is_empty() {
+            return Ok(());
+        }
 
-        const RADIAN_MULTIPLIER: f32 = std::f32::consts::PI / 180.0;
+        let horizon = height as f32 / 2.0;
+        for x in 0..width {
+            let (r, hits_wall, material) = rays[x as usize * rays.len() / width as usize];
+            let wall_color = self.theme.wall_color(material);
+            let column_height = if hits_wall { height as f32 * r } else { 0.0 };
+            let wall_top = (height as f32 - column_height) / 2.0;
+            let wall_bottom = wall_top + column_height;
+
+            for y in 0..height {
+                let y_center = y as f32 + 0.5;
+                let color = if y_center < wall_top {
+                    self.theme.sky
+                } else if y_center < wall_bottom {
+                    wall_color
+                } else {
+                    // The floor fades out towards the horizon, rows further
+                    // down the screen being closer to the player.
+                    let closeness = (y_center - horizon) / horizon;
+                    Renderer::shade(self.theme.floor, 0.4 + 0.6 * closeness)
+                };
+                self.view_frame.set_pixel(x, y, color);
+            }
+        }
 
-        let rotation_rad = rotation * RADIAN_MULTIPLIER;
+        let dest = Rect::new(
+            Renderer::VIEW_X,
+            0,
+            Renderer::VIEW_WIDTH,
+            Renderer::VIEW_HEIGHT,
+        );
+        self.backend
+            .blit_frame(&self.view_frame, dest)
+            .map_err(RendererError::Draw)
+    }
 
-        let s = (rotation_rad).sin();
-        let c = (rotation_rad).cos();
+    fn shade(color: Color, brightness: f32) -> Color {
+        let brightness = brightness.clamp(0.0, 1.0);
+        Color::rgba(
+            (color.r as f32 * brightness) as u8,
+            (color.g as f32 * brightness) as u8,
+            (color.b as f32 * brightness) as u8,
+            color.a,
+        )
+    }
 
-        let t = p - center;
+    /// The color halfway between `a` and `b`.
+    fn blend(a: Color, b: Color) -> Color {
+        let half = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+        Color::rgba(
+            half(a.r, b.r),
+            half(a.g, b.g),
+            half(a.b, b.b),
+            half(a.a, b.a),
+        )
+    }
+}
 
-        let nx = ((t.x as f32) * c - (t.y as f32) * s) + (center.x as f32);
-        let ny = ((t.x as f32) * s + (t.y as f32) * c) + (center.y as f32);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Four rays: a near plain wall, a far wall of material 1, nothing and a
+    /// door.
+    fn rays() -> Vec<(f32, bool, u8)> {
+        vec![
+            (0.5, true, 0),
+            (0.25, true, 1),
+            (0.0, false, 0),
+            (0.5, true, Theme::DOOR_MATERIAL),
+        ]
+    }
+
+    fn render(software: bool) -> Framebuffer {
+        let mut renderer = Renderer::new_headless(800, 330);
+        renderer.set_software_rendering(software);
+        renderer.clear_canvas().unwrap();
+        renderer.draw_walls(rays()).unwrap();
+        renderer.read_pixels().unwrap()
+    }
+
+    /// The middle of the column of ray `ray` at height `y`.
+    fn column_pixel(frame: &Framebuffer, ray: u32, y: u32) -> Color {
+        let column_width = Renderer::VIEW_WIDTH / rays().len() as u32;
+        let x = Renderer::VIEW_X as u32 + column_width * ray + column_width / 2;
+        frame.pixel(x, y).unwrap()
+    }
+
+    #[test]
+    fn walls_render_into_framebuffer() {
+        let frame = render(false);
+        let theme = Theme::default();
+        let middle = Renderer::VIEW_HEIGHT / 2;
+
+        assert_eq!(frame.pixel(10, 10), Some(theme.background));
+        assert_eq!(column_pixel(&frame, 0, 10), theme.sky);
+        assert_eq!(column_pixel(&frame, 0, middle), theme.wall);
+        // Half the view high, from a quarter to three quarters down.
+        assert_eq!(column_pixel(&frame, 0, 76), theme.wall);
+        assert_eq!(column_pixel(&frame, 0, 74), theme.sky);
+        assert_eq!(column_pixel(&frame, 1, middle), theme.wall_color(1));
+        assert_eq!(column_pixel(&frame, 1, 100), theme.sky);
+        assert_eq!(column_pixel(&frame, 2, 100), theme.sky);
+        assert_eq!(column_pixel(&frame, 2, middle + 10), theme.background);
+        assert_eq!(
+            column_pixel(&frame, 3, middle),
+            theme.wall_color(Theme::DOOR_MATERIAL)
+        );
+        assert_eq!(render(false).pixels(), frame.pixels());
+    }
 
-        Point::new(nx as i32, ny as i32)
+    #[test]
+    fn software_walls_match_drawn_walls() {
+        let drawn = render(false);
+        let rasterized = render(true);
+        // Rays that miss leave the lower half to the floor, which only the
+        // software path draws.
+        for (ray, (_, hits_wall, _)) in rays().into_iter().enumerate() {
+            let ray = ray as u32;
+            let middle = if hits_wall {
+                Renderer::VIEW_HEIGHT / 2
+            } else {
+                100
+            };
+            for y in [10, 80, middle] {
+                assert_eq!(
+                    column_pixel(&rasterized, ray, y),
+                    column_pixel(&drawn, ray, y),
+                    "ray {ray} at {y}"
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,385 @@
+use crate::framebuffer::Framebuffer;
+use crate::geometry::{Color, Point, Rect};
+
+/// The parts of the game map the renderer needs to draw it.
+pub struct MapView<'a> {
+    /// Ids of the cells that are walls, `y * width + x`.
+    pub walls: &'a [u16],
+    pub width: u8,
+    pub height: u8,
+    /// Size of a cell in world units.
+    pub block_size: u16,
+    /// One entry per cell, `true` for cells the player has seen. Unexplored
+    /// cells are not drawn, `None` shows the whole map.
+    pub explored: Option<&'a [bool]>,
+    /// Cells push-walls are in right now. They are drawn like any other wall,
+    /// so the map doesn't give them away.
+    pub push_walls: &'a [u16],
+    /// Door cells, `true` for doors that are open far enough to walk through.
+    pub doors: &'a [(u16, bool)],
+    /// Entities to mark on the map, only drawn in explored cells.
+    pub entities: &'a [EntityMarker],
+}
+
+/// What an entity on the map is, which decides the color of its marker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    Enemy,
+    Pickup,
+    Prop,
+}
+
+/// An entity drawn on the map as a square dot, in world units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityMarker {
+    pub position: (f32, f32),
+    pub radius: f32,
+    pub kind: EntityKind,
+}
+
+impl EntityMarker {
+    pub const ENEMY_COLOR: Color = Color::rgb(220, 40, 40);
+    pub const PICKUP_COLOR: Color = Color::rgb(240, 200, 40);
+    pub const PROP_COLOR: Color = Color::rgb(150, 150, 150);
+    /// Markers are never smaller than this, in screen pixels.
+    const MIN_SIZE: f32 = 3.0;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Floor,
+    Wall,
+    ClosedDoor,
+    OpenDoor,
+}
+
+impl MapView<'_> {
+    /// One entry per cell, what is in it.
+    fn cell_grid(&self) -> Vec<Cell> {
+        let mut grid = vec![Cell::Floor; self.width as usize * self.height as usize];
+        for wall in self.walls.iter().chain(self.push_walls) {
+            if let Some(cell) = grid.get_mut(*wall as usize) {
+                *cell = Cell::Wall;
+            }
+        }
+        for (door, open) in self.doors {
+            if let Some(cell) = grid.get_mut(*door as usize) {
+                *cell = if *open {
+                    Cell::OpenDoor
+                } else {
+                    Cell::ClosedDoor
+                };
+            }
+        }
+        grid
+    }
+}
+
+/// Where the player is and what the rays hit, in world units.
+pub struct PlayerView<'a> {
+    pub position: (f32, f32),
+    /// View direction in degrees, as stored by the player.
+    pub rotation: f32,
+    pub ray_ends: &'a [(f32, f32)],
+    /// Where the player's last shot landed, drawn as a tracer while it is
+    /// shown, see `Gunfire`.
+    pub shot: Option<(f32, f32)>,
+}
+
+/// A map widget drawn into a rectangle of the screen.
+///
+/// It either shows the map centered in its frame, or follows the player and
+/// optionally rotates with them so that their view direction always points up.
+/// Anything outside of the frame is clipped.
+pub struct Minimap {
+    frame: Rect,
+    /// Screen pixels per map cell.
+    zoom: f32,
+    follow_player: bool,
+    rotate_with_player: bool,
+    /// Offset of the center of the frame from what it would otherwise show, in cells.
+    pan: (f32, f32),
+    show_grid: bool,
+    show_rays: bool,
+    player_marker: PlayerMarker,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerMarker {
+    Cross,
+    Arrow,
+}
+
+impl Minimap {
+    pub fn new(frame: Rect, zoom: f32) -> Minimap {
+        Minimap {
+            frame,
+            zoom,
+            follow_player: false,
+            rotate_with_player: false,
+            pan: (0.0, 0.0),
+            show_grid: false,
+            show_rays: true,
+            player_marker: PlayerMarker::Cross,
+        }
+    }
+
+    pub fn frame(&self) -> Rect {
+        self.frame
+    }
+    pub fn set_frame(&mut self, frame: Rect) {
+        self.frame = frame;
+    }
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(0.1);
+    }
+    pub fn follow_player(&self) -> bool {
+        self.follow_player
+    }
+    pub fn set_follow_player(&mut self, follow: bool) {
+        self.follow_player = follow;
+    }
+    pub fn rotate_with_player(&self) -> bool {
+        self.rotate_with_player
+    }
+    /// Rotating only makes sense around the player, so this also makes the
+    /// minimap follow them.
+    pub fn set_rotate_with_player(&mut self, rotate: bool) {
+        self.rotate_with_player = rotate;
+        if rotate {
+            self.follow_player = true;
+        }
+    }
+
+    pub fn pan(&self) -> (f32, f32) {
+        self.pan
+    }
+    pub fn set_pan(&mut self, pan: (f32, f32)) {
+        self.pan = pan;
+    }
+    pub fn set_show_grid(&mut self, show: bool) {
+        self.show_grid = show;
+    }
+    pub fn set_show_rays(&mut self, show: bool) {
+        self.show_rays = show;
+    }
+    pub fn set_player_marker(&mut self, marker: PlayerMarker) {
+        self.player_marker = marker;
+    }
+
+    /// Maps a world position to a position on the screen, in the same
+    /// coordinates as the frame.
+    pub fn world_to_screen(
+        &self,
+        map: &MapView,
+        player: &PlayerView,
+        world: (f32, f32),
+    ) -> (f32, f32) {
+        let transform = Transform::new(self, map, player, 1.0);
+        let (x, y) = transform.to_frame(world);
+        (x + self.frame.x as f32, y + self.frame.y as f32)
+    }
+
+    /// Draws the minimap into `target`, which covers the frame at `scale`
+    /// pixels per screen pixel.
+    pub(crate) fn rasterize(
+        &self,
+        target: &mut Framebuffer,
+        scale: f32,
+        map: &MapView,
+        player: &PlayerView,
+        colors: &MinimapColors,
+    ) {
+        let transform = Transform::new(self, map, player, scale);
+        let cells = map.cell_grid();
+        let block_size = map.block_size as f32;
+        // How much of a cell one pixel covers, for one pixel wide grid lines.
+        let grid_width = 1.0 / (self.zoom * scale);
+
+        for y in 0..target.height() {
+            for x in 0..target.width() {
+                let (world_x, world_y) = transform.to_world((x as f32 + 0.5, y as f32 + 0.5));
+                let (cell_x, cell_y) = (world_x / block_size, world_y / block_size);
+                let cell = cell_y as usize * map.width as usize + cell_x as usize;
+                let color = if cell_x < 0.0
+                    || cell_y < 0.0
+                    || cell_x >= map.width as f32
+                    || cell_y >= map.height as f32
+                    || map
+                        .explored
+                        .is_some_and(|explored| !explored.get(cell).copied().unwrap_or(false))
+                {
+                    colors.background
+                } else if self.show_grid
+                    && (cell_x.fract() < grid_width || cell_y.fract() < grid_width)
+                {
+                    colors.grid
+                } else {
+                    match cells[cell] {
+                        Cell::Floor => colors.floor,
+                        Cell::Wall => colors.wall,
+                        Cell::ClosedDoor => colors.closed_door,
+                        Cell::OpenDoor => colors.open_door,
+                    }
+                };
+                target.set_pixel(x, y, color);
+            }
+        }
+
+        let to_point = |world: (f32, f32)| {
+            let (x, y) = transform.to_frame(world);
+            Point::new(x as i32, y as i32)
+        };
+        let origin = to_point(player.position);
+        if self.show_rays {
+            for ray_end in player.ray_ends {
+                target.draw_line(origin, to_point(*ray_end), colors.ray);
+            }
+        }
+
+        for entity in map.entities {
+            let cell = (entity.position.1 / block_size) as usize * map.width as usize
+                + (entity.position.0 / block_size) as usize;
+            if map
+                .explored
+                .is_some_and(|explored| !explored.get(cell).copied().unwrap_or(false))
+            {
+                continue;
+            }
+            let color = match entity.kind {
+                EntityKind::Enemy => colors.enemy,
+                EntityKind::Pickup => colors.pickup,
+                EntityKind::Prop => colors.prop,
+            };
+            let (center_x, center_y) = transform.to_frame(entity.position);
+            let half = (entity.radius * transform.pixels_per_unit)
+                .max(EntityMarker::MIN_SIZE * scale / 2.0);
+            let x_range = (center_x - half).max(0.0) as u32
+                ..((center_x + half).max(0.0) as u32).min(target.width());
+            for y in (center_y - half).max(0.0) as u32
+                ..((center_y + half).max(0.0) as u32).min(target.height())
+            {
+                for x in x_range.clone() {
+                    target.set_pixel(x, y, color);
+                }
+            }
+        }
+
+        if let Some(shot) = player.shot {
+            target.draw_line(origin, to_point(shot), colors.tracer);
+        }
+
+        // The long end of the cross and the tip of the arrow point the way
+        // the player is looking.
+        let rotation = (player.rotation + transform.rotation).to_radians();
+        let (sin, cos) = rotation.sin_cos();
+        let arm = |length: f32, across: f32| {
+            let length = length * scale;
+            let across = across * scale;
+            Point::new(
+                origin.x + (length * cos - across * sin) as i32,
+                origin.y + (length * sin + across * cos) as i32,
+            )
+        };
+        match self.player_marker {
+            PlayerMarker::Cross => {
+                target.draw_line(arm(0.0, 3.0), arm(0.0, -3.0), colors.player);
+                target.draw_line(arm(-5.0, 0.0), arm(2.0, 0.0), colors.player);
+            }
+            PlayerMarker::Arrow => {
+                let (tip, tail) = (arm(-8.0, 0.0), arm(6.0, 0.0));
+                target.draw_line(tail, tip, colors.player);
+                target.draw_line(tip, arm(-3.0, 4.0), colors.player);
+                target.draw_line(tip, arm(-3.0, -4.0), colors.player);
+                target.draw_line(tail, arm(9.0, 3.0), colors.player);
+                target.draw_line(tail, arm(9.0, -3.0), colors.player);
+            }
+        }
+    }
+}
+
+pub(crate) struct MinimapColors {
+    pub background: Color,
+    pub wall: Color,
+    pub floor: Color,
+    pub closed_door: Color,
+    pub open_door: Color,
+    pub grid: Color,
+    pub player: Color,
+    pub ray: Color,
+    pub tracer: Color,
+    pub enemy: Color,
+    pub pickup: Color,
+    pub prop: Color,
+}
+
+/// World to frame transform: translate to the center, rotate, then scale.
+struct Transform {
+    center: (f32, f32),
+    frame_center: (f32, f32),
+    pixels_per_unit: f32,
+    /// In degrees.
+    rotation: f32,
+    sin: f32,
+    cos: f32,
+}
+
+impl Transform {
+    fn new(minimap: &Minimap, map: &MapView, player: &PlayerView, scale: f32) -> Transform {
+        let block_size = map.block_size as f32;
+        let (center_x, center_y) = if minimap.follow_player {
+            player.position
+        } else {
+            (
+                map.width as f32 * block_size / 2.0,
+                map.height as f32 * block_size / 2.0,
+            )
+        };
+        let center = (
+            center_x + minimap.pan.0 * block_size,
+            center_y + minimap.pan.1 * block_size,
+        );
+        // The player moves against their view direction vector, see
+        // `Player::update_position`, so looking "up" on the screen means
+        // rotating that vector to point down.
+        let rotation = if minimap.rotate_with_player {
+            90.0 - player.rotation
+        } else {
+            0.0
+        };
+        let (sin, cos) = rotation.to_radians().sin_cos();
+
+        Transform {
+            center,
+            frame_center: (
+                minimap.frame.width as f32 * scale / 2.0,
+                minimap.frame.height as f32 * scale / 2.0,
+            ),
+            pixels_per_unit: minimap.zoom * scale / block_size,
+            rotation,
+            sin,
+            cos,
+        }
+    }
+
+    fn to_frame(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (dx, dy) = (x - self.center.0, y - self.center.1);
+        let (rx, ry) = (dx * self.cos - dy * self.sin, dx * self.sin + dy * self.cos);
+        (
+            rx * self.pixels_per_unit + self.frame_center.0,
+            ry * self.pixels_per_unit + self.frame_center.1,
+        )
+    }
+
+    fn to_world(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let rx = (x - self.frame_center.0) / self.pixels_per_unit;
+        let ry = (y - self.frame_center.1) / self.pixels_per_unit;
+        (
+            rx * self.cos + ry * self.sin + self.center.0,
+            -rx * self.sin + ry * self.cos + self.center.1,
+        )
+    }
+}
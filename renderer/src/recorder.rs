@@ -0,0 +1,128 @@
+use crate::error::RendererError;
+use crate::framebuffer::Framebuffer;
+use crate::gif::GifEncoder;
+use crate::image::{self, ImageFormat};
+use crate::Renderer;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+enum Output {
+    Sequence {
+        dir: PathBuf,
+        format: ImageFormat,
+    },
+    /// The encoder is created with the first frame, which decides the size.
+    Gif(Option<GifEncoder<BufWriter<File>>>),
+}
+
+/// Captures rendered frames into a numbered image sequence or an animated GIF.
+///
+/// Frames are captured at a fixed rate no matter how fast the game runs, so
+/// clips play back at the speed they were recorded at.
+pub struct FrameRecorder {
+    output: Output,
+    path: PathBuf,
+    frame_interval: Duration,
+    since_last_frame: Duration,
+    frames: u32,
+}
+
+impl FrameRecorder {
+    pub const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Records into `path`: a `.gif` file becomes an animated GIF, anything
+    /// else is taken as a directory to write a `frame_00000.bmp` style image
+    /// sequence to.
+    pub fn new(path: &Path, frame_interval: Duration) -> Result<FrameRecorder, RendererError> {
+        let output = match ImageFormat::from_path(path) {
+            Some(ImageFormat::Gif) => Output::Gif(None),
+            Some(format) => {
+                return Err(RendererError::Asset(format!(
+                    "Can not record into a single .{} file, use a .gif or a directory",
+                    format.extension()
+                )))
+            }
+            None => {
+                std::fs::create_dir_all(path).map_err(|e| {
+                    RendererError::Asset(format!("Could not create {}: {e}", path.display()))
+                })?;
+                Output::Sequence {
+                    dir: path.to_path_buf(),
+                    format: ImageFormat::Bmp,
+                }
+            }
+        };
+
+        Ok(FrameRecorder {
+            output,
+            path: path.to_path_buf(),
+            frame_interval,
+            // The first frame is always captured.
+            since_last_frame: frame_interval,
+            frames: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Call once per rendered frame, before presenting it, with the game time
+    /// that passed since the previous one.
+    pub fn capture(
+        &mut self,
+        renderer: &mut Renderer,
+        elapsed: Duration,
+    ) -> Result<(), RendererError> {
+        self.since_last_frame += elapsed;
+        if self.since_last_frame < self.frame_interval {
+            return Ok(());
+        }
+        self.since_last_frame -= self.frame_interval;
+
+        let frame = renderer.read_pixels()?;
+        self.add_frame(&frame).map_err(RendererError::Asset)
+    }
+
+    fn add_frame(&mut self, frame: &Framebuffer) -> Result<(), String> {
+        match &mut self.output {
+            Output::Sequence { dir, format } => {
+                let file_name = format!("frame_{:05}.{}", self.frames, format.extension());
+                image::write_image(frame, &dir.join(file_name), *format)?;
+            }
+            Output::Gif(encoder) => {
+                if encoder.is_none() {
+                    let file = File::create(&self.path)
+                        .map_err(|e| format!("Could not create {}: {e}", self.path.display()))?;
+                    *encoder = Some(GifEncoder::new(
+                        BufWriter::new(file),
+                        frame.width(),
+                        frame.height(),
+                        true,
+                    )?);
+                }
+                let delay = (self.frame_interval.as_millis() / 10) as u16;
+                if let Some(encoder) = encoder {
+                    encoder.add_frame(frame, delay)?;
+                }
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Finishes the recording, which is needed for GIFs to be valid.
+    pub fn finish(self) -> Result<(), RendererError> {
+        match self.output {
+            Output::Gif(Some(encoder)) => {
+                encoder.finish().map(|_| ()).map_err(RendererError::Asset)
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::backend::Backend;
+use crate::framebuffer::Framebuffer;
+use crate::geometry::{Color, Point, Rect};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::Texture;
+
+/// Draws into a window through an SDL canvas.
+#[allow(dead_code)]
+pub struct SdlBackend {
+    video_subsystem: sdl2::VideoSubsystem,
+    canvas: sdl2::render::Canvas<sdl2::video::Window>,
+    /// Used by `blit_frame`, one per frame size and reused between frames.
+    streaming_textures: Vec<Texture>,
+}
+
+impl SdlBackend {
+    pub fn new(
+        sdl_context: &sdl2::Sdl,
+        width: u32,
+        height: u32,
+        title: &str,
+    ) -> Result<SdlBackend, String> {
+        let video_subsystem = sdl_context.video()?;
+        let window = video_subsystem
+            .window(title, width, height)
+            .position_centered()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+
+        Ok(SdlBackend {
+            video_subsystem,
+            canvas,
+            streaming_textures: Vec::new(),
+        })
+    }
+
+    fn streaming_texture(&mut self, width: u32, height: u32) -> Result<usize, String> {
+        let existing = self.streaming_textures.iter().position(|texture| {
+            let query = texture.query();
+            query.width == width && query.height == height
+        });
+        if let Some(index) = existing {
+            return Ok(index);
+        }
+
+        let texture = self
+            .canvas
+            .create_texture_streaming(PixelFormatEnum::RGBA32, width, height)
+            .map_err(|e| e.to_string())?;
+        self.streaming_textures.push(texture);
+        Ok(self.streaming_textures.len() - 1)
+    }
+}
+
+fn sdl_color(color: Color) -> sdl2::pixels::Color {
+    sdl2::pixels::Color::RGBA(color.r, color.g, color.b, color.a)
+}
+
+fn sdl_point(point: Point) -> sdl2::rect::Point {
+    sdl2::rect::Point::new(point.x, point.y)
+}
+
+fn sdl_rect(rect: Rect) -> sdl2::rect::Rect {
+    sdl2::rect::Rect::new(rect.x, rect.y, rect.width, rect.height)
+}
+
+impl Backend for SdlBackend {
+    fn size(&self) -> (u32, u32) {
+        self.canvas.window().size()
+    }
+
+    fn set_scale(&mut self, scale: f32) -> Result<(), String> {
+        self.canvas.set_scale(scale, scale)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), String> {
+        self.canvas.set_draw_color(sdl_color(color));
+        self.canvas.clear();
+        Ok(())
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<(), String> {
+        self.canvas.set_draw_color(sdl_color(color));
+        self.canvas.fill_rect(sdl_rect(rect))
+    }
+
+    fn fill_rects(&mut self, rects: &[Rect], color: Color) -> Result<(), String> {
+        let rects = rects.iter().map(|r| sdl_rect(*r)).collect::<Vec<_>>();
+        self.canvas.set_draw_color(sdl_color(color));
+        self.canvas.fill_rects(&rects)
+    }
+
+    fn draw_line(&mut self, start: Point, end: Point, color: Color) -> Result<(), String> {
+        self.canvas.set_draw_color(sdl_color(color));
+        self.canvas.draw_line(sdl_point(start), sdl_point(end))
+    }
+
+    fn blit_frame(&mut self, frame: &Framebuffer, dest: Rect) -> Result<(), String> {
+        let index = self.streaming_texture(frame.width(), frame.height())?;
+        let texture = &mut self.streaming_textures[index];
+        texture
+            .update(None, frame.pixels(), (frame.width() * 4) as usize)
+            .map_err(|e| e.to_string())?;
+        self.canvas.copy(texture, None, Some(sdl_rect(dest)))
+    }
+
+    fn present(&mut self) -> Result<(), String> {
+        self.canvas.present();
+        Ok(())
+    }
+
+    fn read_pixels(&mut self) -> Result<Framebuffer, String> {
+        let (width, height) = self.canvas.output_size()?;
+        let pixels = self.canvas.read_pixels(None, PixelFormatEnum::RGBA32)?;
+        Framebuffer::from_pixels(width, height, pixels)
+    }
+}
@@ -0,0 +1,305 @@
+use crate::backend::Backend;
+use crate::framebuffer::Framebuffer;
+use crate::geometry::{Color, Point, Rect};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// Draws into the terminal using ANSI truecolor escape codes.
+///
+/// Frames are drawn into a framebuffer first and scaled down on `present`, each
+/// character cell showing two pixels stacked on top of each other by coloring
+/// the foreground and background of an upper half block.
+pub struct TerminalBackend {
+    framebuffer: Framebuffer,
+    columns: u32,
+    rows: u32,
+}
+
+impl TerminalBackend {
+    /// `width` and `height` are the size of the virtual canvas the renderer
+    /// draws on, `columns` and `rows` the size of the terminal it is shown in.
+    pub fn new(width: u32, height: u32, columns: u32, rows: u32) -> TerminalBackend {
+        // Alternate screen, hidden cursor.
+        print!("\x1b[?1049h\x1b[?25l");
+
+        TerminalBackend {
+            framebuffer: Framebuffer::new(width, height),
+            columns,
+            rows,
+        }
+    }
+
+    /// The terminal size in characters, as reported by `stty`.
+    pub fn terminal_size() -> Option<(u32, u32)> {
+        let output = Command::new("stty")
+            .arg("size")
+            .stdin(tty()?)
+            .output()
+            .ok()?;
+        let size = String::from_utf8(output.stdout).ok()?;
+        let mut values = size.split_whitespace().map(|v| v.parse::<u32>().ok());
+        let rows = values.next()??;
+        let columns = values.next()??;
+        Some((columns, rows))
+    }
+
+    /// Averages the pixels of the framebuffer falling into a single sub-cell.
+    fn sample(&self, column: u32, pixel_row: u32) -> Color {
+        let (width, height) = (self.framebuffer.width(), self.framebuffer.height());
+        let x_start = column * width / self.columns;
+        let x_end = ((column + 1) * width / self.columns).max(x_start + 1);
+        let y_start = pixel_row * height / (self.rows * 2);
+        let y_end = ((pixel_row + 1) * height / (self.rows * 2)).max(y_start + 1);
+
+        let (mut r, mut g, mut b, mut count) = (0u32, 0u32, 0u32, 0u32);
+        for y in y_start..y_end {
+            for x in x_start..x_end {
+                if let Some(color) = self.framebuffer.pixel(x, y) {
+                    r += color.r as u32;
+                    g += color.g as u32;
+                    b += color.b as u32;
+                    count += 1;
+                }
+            }
+        }
+        let count = count.max(1);
+        Color::rgb((r / count) as u8, (g / count) as u8, (b / count) as u8)
+    }
+}
+
+impl Drop for TerminalBackend {
+    fn drop(&mut self) {
+        // Reset colors, show the cursor and leave the alternate screen.
+        print!("\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = std::io::stdout().flush();
+    }
+}
+
+impl Backend for TerminalBackend {
+    fn size(&self) -> (u32, u32) {
+        self.framebuffer.size()
+    }
+
+    fn set_scale(&mut self, scale: f32) -> Result<(), String> {
+        self.framebuffer.set_scale(scale)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), String> {
+        self.framebuffer.clear(color);
+        Ok(())
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<(), String> {
+        self.framebuffer.fill_rect(rect, color);
+        Ok(())
+    }
+
+    fn draw_line(&mut self, start: Point, end: Point, color: Color) -> Result<(), String> {
+        self.framebuffer.draw_line(start, end, color);
+        Ok(())
+    }
+
+    fn blit_frame(&mut self, frame: &Framebuffer, dest: Rect) -> Result<(), String> {
+        self.framebuffer.blit(frame, dest);
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), String> {
+        let mut out = String::from("\x1b[H");
+        let mut last = None;
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let top = self.sample(column, row * 2);
+                let bottom = self.sample(column, row * 2 + 1);
+                if last != Some((top, bottom)) {
+                    let _ = write!(
+                        out,
+                        "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                        top.r, top.g, top.b, bottom.r, bottom.g, bottom.b
+                    );
+                    last = Some((top, bottom));
+                }
+                out.push('▀');
+            }
+            out.push_str("\x1b[0m\r\n");
+            last = None;
+        }
+
+        let mut stdout = std::io::stdout().lock();
+        stdout
+            .write_all(out.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|e| e.to_string())
+    }
+
+    fn framebuffer(&self) -> Option<&Framebuffer> {
+        Some(&self.framebuffer)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TerminalKey {
+    Char(char),
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Reads key presses from the terminal, which is switched to raw mode for as
+/// long as this is alive.
+///
+/// Terminals only report key presses, never releases, so a key counts as held
+/// for a short while after its last press. Holding a key down keeps it held
+/// through the terminal's key repeat.
+pub struct TerminalInput {
+    keys: Receiver<TerminalKey>,
+    last_pressed: HashMap<TerminalKey, Instant>,
+    saved_mode: Option<String>,
+}
+
+impl TerminalInput {
+    /// Long enough to bridge the delay before the terminal starts repeating a key.
+    const HOLD_TIME: Duration = Duration::from_millis(550);
+
+    pub fn new() -> Result<TerminalInput, String> {
+        let saved_mode = stty(&["-g"])?;
+        // Reads give up after a tenth of a second without input, which tells
+        // the escape key apart from the start of an escape sequence.
+        stty(&["raw", "-echo", "min", "0", "time", "1"])?;
+
+        let (sender, keys) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut stdin = std::io::stdin();
+            let mut buffer = [0u8; 64];
+            // The start of an escape sequence the last read cut off.
+            let mut pending = Vec::new();
+            while let Ok(read) = stdin.read(&mut buffer) {
+                // Only a terminal times out, anything else has ended.
+                if read == 0 && !stdin.is_terminal() {
+                    break;
+                }
+                pending.extend_from_slice(&buffer[..read]);
+                let (keys, used) = parse_keys(&pending, read == 0);
+                pending.drain(..used);
+                for key in keys {
+                    if sender.send(key).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(TerminalInput {
+            keys,
+            last_pressed: HashMap::new(),
+            saved_mode: Some(saved_mode.trim().to_string()),
+        })
+    }
+
+    /// Returns the keys pressed since the last call.
+    pub fn poll(&mut self) -> Vec<TerminalKey> {
+        let now = Instant::now();
+        let pressed = self.keys.try_iter().collect::<Vec<TerminalKey>>();
+        for key in &pressed {
+            self.last_pressed.insert(*key, now);
+        }
+        pressed
+    }
+
+    pub fn is_held(&self, key: TerminalKey) -> bool {
+        self.last_pressed
+            .get(&key)
+            .is_some_and(|time| time.elapsed() < TerminalInput::HOLD_TIME)
+    }
+}
+
+impl Drop for TerminalInput {
+    fn drop(&mut self) {
+        if let Some(mode) = self.saved_mode.take() {
+            let _ = stty(&[&mode]);
+        }
+    }
+}
+
+fn tty() -> Option<Stdio> {
+    std::fs::File::open("/dev/tty").ok().map(Stdio::from)
+}
+
+fn stty(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(tty().ok_or("No terminal available")?)
+        .output()
+        .map_err(|e| format!("Could not run stty: {e}"))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Turns bytes read from the terminal into keys. Returns the keys and how many
+/// bytes they used: an escape sequence cut off at the end is left for the next
+/// read to complete, unless `timed_out` says nothing more came, in which case
+/// the escape was the escape key.
+fn parse_keys(bytes: &[u8], timed_out: bool) -> (Vec<TerminalKey>, usize) {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            0x1b if !timed_out && (bytes.len() == i + 1 || bytes[i + 1..] == *b"[") => break,
+            0x1b if bytes.get(i + 1) == Some(&b'[') && i + 2 < bytes.len() => {
+                match bytes[i + 2] {
+                    b'A' => keys.push(TerminalKey::Up),
+                    b'B' => keys.push(TerminalKey::Down),
+                    b'C' => keys.push(TerminalKey::Right),
+                    b'D' => keys.push(TerminalKey::Left),
+                    _ => {}
+                }
+                i += 3;
+                continue;
+            }
+            0x1b => keys.push(TerminalKey::Escape),
+            // Ctrl+C does not raise a signal in raw mode.
+            0x03 => keys.push(TerminalKey::Escape),
+            byte if byte.is_ascii() => keys.push(TerminalKey::Char(byte as char)),
+            _ => {}
+        }
+        i += 1;
+    }
+    (keys, i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrows_split_across_reads_are_kept_for_the_next_one() {
+        let (keys, used) = parse_keys(b"w\x1b[", false);
+        assert_eq!((keys, used), (vec![TerminalKey::Char('w')], 1));
+        let (keys, used) = parse_keys(b"\x1b[Dw", false);
+        assert_eq!(
+            (keys, used),
+            (vec![TerminalKey::Left, TerminalKey::Char('w')], 4)
+        );
+
+        let (keys, used) = parse_keys(b"\x1b", false);
+        assert_eq!((keys, used), (vec![], 0));
+    }
+
+    #[test]
+    fn a_lone_escape_is_the_escape_key_once_reads_time_out() {
+        assert_eq!(parse_keys(b"\x1b", true), (vec![TerminalKey::Escape], 1));
+        assert_eq!(
+            parse_keys(b"\x1bq", false),
+            (vec![TerminalKey::Escape, TerminalKey::Char('q')], 2)
+        );
+        assert_eq!(parse_keys(b"", true), (vec![], 0));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::error::RendererError;
+use crate::geometry::Color;
+use std::fs;
+use std::path::Path;
+
+/// A named set of colors for everything the renderer draws.
+///
+/// Walls can have a material, material `0` being plain walls drawn in `wall`
+/// and every other material getting its own color. Materials without a color
+/// fall back to `wall`. Doors are walls of material `DOOR_MATERIAL`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    pub wall: Color,
+    pub floor: Color,
+    pub player: Color,
+    pub ray: Color,
+    pub sky: Color,
+    /// Colors of materials `1` and up, indexed by material minus one.
+    pub materials: Vec<Option<Color>>,
+}
+
+impl Theme {
+    /// The material the game gives door cells.
+    pub const DOOR_MATERIAL: u8 = 10;
+
+    /// The themes that are always available, the first one being the default.
+    pub fn builtin() -> Vec<Theme> {
+        let classic = Theme::default();
+        let mut night = Theme {
+            name: "night".to_string(),
+            background: Color::rgb(5, 5, 15),
+            wall: Color::rgb(70, 75, 100),
+            floor: Color::rgb(25, 35, 45),
+            player: Color::rgb(255, 220, 90),
+            ray: Color::rgb(90, 90, 150),
+            sky: Color::rgb(10, 15, 40),
+            materials: vec![
+                Some(Color::rgb(110, 50, 60)),
+                Some(Color::rgb(50, 90, 110)),
+                Some(Color::rgb(120, 110, 60)),
+            ],
+        };
+        let mut sepia = Theme {
+            name: "sepia".to_string(),
+            background: Color::rgb(40, 30, 20),
+            wall: Color::rgb(170, 140, 100),
+            floor: Color::rgb(110, 85, 55),
+            player: Color::rgb(90, 40, 20),
+            ray: Color::rgb(220, 190, 140),
+            sky: Color::rgb(230, 210, 170),
+            materials: vec![
+                Some(Color::rgb(140, 90, 60)),
+                Some(Color::rgb(120, 110, 90)),
+                Some(Color::rgb(190, 160, 90)),
+            ],
+        };
+        night.set_material_color(Theme::DOOR_MATERIAL, Color::rgb(90, 70, 40));
+        sepia.set_material_color(Theme::DOOR_MATERIAL, Color::rgb(100, 60, 30));
+        vec![classic, night, sepia]
+    }
+
+    /// Color of a wall made of `material`.
+    pub fn wall_color(&self, material: u8) -> Color {
+        match material {
+            0 => self.wall,
+            _ => self
+                .materials
+                .get(material as usize - 1)
+                .copied()
+                .flatten()
+                .unwrap_or(self.wall),
+        }
+    }
+
+    pub fn set_material_color(&mut self, material: u8, color: Color) {
+        if material == 0 {
+            self.wall = color;
+            return;
+        }
+        let index = material as usize - 1;
+        if self.materials.len() <= index {
+            self.materials.resize(index + 1, None);
+        }
+        self.materials[index] = Some(color);
+    }
+
+    pub fn load(path: &Path) -> Result<Vec<Theme>, RendererError> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| RendererError::Asset(format!("Could not read {}: {e}", path.display())))?;
+        Theme::parse(&text)
+    }
+
+    /// Parses any number of themes. Each one starts with `theme <name>`,
+    /// followed by lines of `<part> <r> <g> <b>` for `background`, `wall`,
+    /// `floor`, `player`, `ray` and `sky`, or `material <id> <r> <g> <b>`.
+    /// Anything left out keeps the color of the default theme, lines starting
+    /// with `#` are comments.
+    pub fn parse(text: &str) -> Result<Vec<Theme>, RendererError> {
+        let mut themes: Vec<Theme> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let bad_line =
+                || RendererError::Asset(format!("Malformed theme line {line_no}: '{line}'"));
+
+            let words = line.split_whitespace().collect::<Vec<&str>>();
+            if words.is_empty() || words[0].starts_with('#') {
+                continue;
+            }
+            if let ["theme", name] = words[..] {
+                themes.push(Theme {
+                    name: name.to_string(),
+                    ..Theme::default()
+                });
+                continue;
+            }
+
+            let theme = themes.last_mut().ok_or_else(|| {
+                RendererError::Asset(format!(
+                    "Line {line_no} comes before any 'theme <name>' line"
+                ))
+            })?;
+            let values = words[1..]
+                .iter()
+                .map(|word| word.parse::<u8>())
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| bad_line())?;
+            match (words[0], &values[..]) {
+                ("material", [id, r, g, b]) => {
+                    theme.set_material_color(*id, Color::rgb(*r, *g, *b))
+                }
+                (part, [r, g, b]) => {
+                    let color = Color::rgb(*r, *g, *b);
+                    match part {
+                        "background" => theme.background = color,
+                        "wall" => theme.wall = color,
+                        "floor" => theme.floor = color,
+                        "player" => theme.player = color,
+                        "ray" => theme.ray = color,
+                        "sky" => theme.sky = color,
+                        _ => return Err(bad_line()),
+                    }
+                }
+                _ => return Err(bad_line()),
+            }
+        }
+
+        if themes.is_empty() {
+            return Err(RendererError::Asset(
+                "No themes found, expected a 'theme <name>' line".to_string(),
+            ));
+        }
+        Ok(themes)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        let mut theme = Theme {
+            name: "classic".to_string(),
+            background: Color::rgb(0, 0, 0),
+            wall: Color::rgb(147, 151, 153),
+            floor: Color::rgb(52, 140, 49),
+            player: Color::rgb(0, 0, 255),
+            ray: Color::rgb(0, 191, 255),
+            sky: Color::rgb(135, 206, 235),
+            materials: vec![
+                Some(Color::rgb(160, 82, 45)),
+                Some(Color::rgb(112, 128, 144)),
+                Some(Color::rgb(184, 134, 11)),
+            ],
+        };
+        theme.set_material_color(Theme::DOOR_MATERIAL, Color::rgb(139, 90, 43));
+        theme
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_themes_over_the_default() {
+        let themes = Theme::parse(
+            "\
+# Comments and blank lines are skipped.
+
+theme dusk
+wall 10 20 30
+material 5 200 100 0
+theme plain
+",
+        )
+        .unwrap();
+
+        let mut dusk = Theme {
+            name: "dusk".to_string(),
+            wall: Color::rgb(10, 20, 30),
+            ..Theme::default()
+        };
+        dusk.set_material_color(5, Color::rgb(200, 100, 0));
+        let plain = Theme {
+            name: "plain".to_string(),
+            ..Theme::default()
+        };
+        assert_eq!(themes, vec![dusk, plain]);
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        let error = |text: &str| match Theme::parse(text) {
+            Err(RendererError::Asset(message)) => message,
+            other => panic!("Expected an asset error for {text:?}, got {other:?}"),
+        };
+
+        for line in [
+            "wall 1 2",
+            "wall 1 2 3 4",
+            "wall 256 0 0",
+            "wall red 0 0",
+            "glow 1 2 3",
+        ] {
+            let message = error(&format!("theme broken\n{line}"));
+            assert_eq!(message, format!("Malformed theme line 2: '{line}'"));
+        }
+        assert!(error("wall 1 2 3").contains("before any 'theme <name>' line"));
+        assert!(error("# nothing here").starts_with("No themes found"));
+    }
+}
@@ -0,0 +1,130 @@
+use gamestate::Gamestate;
+use renderer::{Color, Renderer, Theme};
+use std::path::Path;
+
+const HELP: &str = "fov <degrees>        field of view
+rays <count>         number of rays cast
+noclip               walk through walls
+tp <x> <y>           teleport to a cell
+load <file>          load a map, # or 1-9 walls, . floor, P the start,
+                     D doors, a-z doors locked with key 1-26, S secret
+                     walls, E enemies, H health, A ammo, T treasure, K keys
+theme [name]         list the themes or switch to one
+set <name> <values>  wall_color, floor_color, sky_color, background_color,
+                     player_color, ray_color as r g b, scale, software 0|1
+clear                clear the console";
+
+/// Runs a line typed into the console and returns what to print.
+///
+/// Commands that move the player or replace the map would make recordings
+/// diverge from what was played, so they are refused unless `can_change_game`
+/// is set.
+pub fn execute(
+    line: &str,
+    gamestate: &mut Gamestate,
+    renderer: &mut Renderer,
+    themes: &[Theme],
+    can_change_game: bool,
+) -> Result<String, String> {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or_default();
+    let args = words.collect::<Vec<&str>>();
+
+    if !can_change_game && matches!(command, "noclip" | "tp" | "load") {
+        return Err(format!(
+            "{command} is not available while recording or playing back"
+        ));
+    }
+
+    match (command, &args[..]) {
+        ("help", []) => Ok(HELP.to_string()),
+        ("clear", []) => {
+            renderer.console_mut().clear_log();
+            Ok(String::new())
+        }
+        ("fov", []) => Ok(format!("fov {}", gamestate.field_of_view())),
+        ("fov", [fov]) => {
+            gamestate.set_field_of_view(parse(fov)?)?;
+            Ok(format!("fov {}", gamestate.field_of_view()))
+        }
+        ("rays", []) => Ok(format!("rays {}", gamestate.ray_count())),
+        ("rays", [count]) => {
+            gamestate.set_ray_count(parse(count)?)?;
+            Ok(format!("rays {}", gamestate.ray_count()))
+        }
+        ("noclip", []) => {
+            gamestate.set_noclip(!gamestate.noclip());
+            Ok(format!(
+                "noclip {}",
+                if gamestate.noclip() { "on" } else { "off" }
+            ))
+        }
+        ("tp", [x, y]) => {
+            let (x, y) = (parse(x)?, parse(y)?);
+            gamestate.teleport(x, y)?;
+            Ok(format!("Teleported to {x} {y}"))
+        }
+        ("load", [path]) => {
+            gamestate.load_map(Path::new(path))?;
+            Ok(format!(
+                "Loaded {path}, {}x{}",
+                gamestate.map_width(),
+                gamestate.map_height()
+            ))
+        }
+        ("theme", []) => Ok(themes
+            .iter()
+            .map(|theme| {
+                let current = if theme.name == renderer.theme().name {
+                    "*"
+                } else {
+                    " "
+                };
+                format!("{current} {}", theme.name)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")),
+        ("theme", [name]) => {
+            let theme = themes
+                .iter()
+                .find(|theme| theme.name == *name)
+                .ok_or_else(|| format!("Unknown theme '{name}'"))?;
+            renderer.set_theme(theme.clone());
+            Ok(format!("theme {name}"))
+        }
+        ("set", [name, values @ ..]) => set(name, values, renderer),
+        _ => Err(format!("Unknown command '{line}', try help")),
+    }
+}
+
+fn set(name: &str, values: &[&str], renderer: &mut Renderer) -> Result<String, String> {
+    let color = || match values {
+        [r, g, b] => Ok(Color::rgb(parse(r)?, parse(g)?, parse(b)?)),
+        _ => Err(format!("{name} takes three values from 0 to 255")),
+    };
+    match name {
+        "wall_color" => renderer.set_wall_color(color()?),
+        "floor_color" => renderer.set_floor_color(color()?),
+        "sky_color" => renderer.set_sky_color(color()?),
+        "background_color" => renderer.set_background_color(color()?),
+        "player_color" => renderer.set_player_color(color()?),
+        "ray_color" => renderer.set_ray_color(color()?),
+        "scale" => match values {
+            [scale] => renderer.set_scale(parse(scale)?)?,
+            _ => return Err("scale takes one value".to_string()),
+        },
+        "software" => match values {
+            ["0"] => renderer.set_software_rendering(false),
+            ["1"] => renderer.set_software_rendering(true),
+            _ => return Err("software takes 0 or 1".to_string()),
+        },
+        _ => return Err(format!("Unknown setting '{name}'")),
+    }
+    Ok(format!("{name} {}", values.join(" ")))
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value '{value}'"))
+}
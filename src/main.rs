@@ -1,10 +1,185 @@
-use gamestate::{MoveDirection, TurnDirection};
+use gamestate::{
+    Action, Behaviour, EnemyAi, Entity, Event as GameEvent, Hit, Item, Level, Recording, Target,
+    TickInput,
+};
+use renderer::{
+    DebugStats, EntityKind, EntityMarker, FrameRecorder, ImageFormat, Impact, MapView, Minimap,
+    PlayerView, RendererError, Status, TerminalBackend, TerminalInput, TerminalKey, Theme,
+};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use std::f32;
+use std::path::PathBuf;
+
+mod commands;
+
+const MAP_ID: &str = "builtin";
+
+const USAGE: &str = "Usage: raycaster-in-rust [--record <file> | --play <file>] \
+                     [--headless <image.bmp|ppm> | --terminal] [--software] \
+                     [--export <clip.gif|frame directory>] [--themes <file>]";
+
+const SCREENSHOT_DIR: &str = "screenshots";
+const CLIP_DIR: &str = "clips";
+const SAVE_DIR: &str = "saves";
+const SAVE_SLOTS: u32 = 3;
+
+const WINDOW_WIDTH: u32 = 1250;
+const WINDOW_HEIGHT: u32 = 500;
+
+enum Mode {
+    Interactive,
+    Record(PathBuf),
+    Playback(Recording),
+}
+
+struct Options {
+    mode: Mode,
+    /// Render without a window and write the last frame to this file, as a BMP
+    /// or PPM depending on its extension.
+    headless: Option<PathBuf>,
+    /// Draw into the terminal and read input from it instead of opening a window.
+    terminal: bool,
+    /// Start with the software rendering path of the 3D view, F2 toggles it.
+    software: bool,
+    /// Record every frame into an animated GIF or an image sequence, mostly
+    /// useful together with `--play`. F9 starts and stops clips while playing.
+    export: Option<PathBuf>,
+    /// Extra themes to choose from besides the built-in ones.
+    themes: Option<PathBuf>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        mode: Mode::Interactive,
+        headless: None,
+        terminal: false,
+        software: false,
+        export: None,
+        themes: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--terminal" => {
+                options.terminal = true;
+                continue;
+            }
+            "--software" => {
+                options.software = true;
+                continue;
+            }
+            _ => {}
+        }
+
+        let value = args.next().ok_or(USAGE)?;
+        match arg.as_str() {
+            "--record" => options.mode = Mode::Record(PathBuf::from(value)),
+            "--play" => {
+                let recording = Recording::load(&PathBuf::from(value))?;
+                if recording.map_id() != MAP_ID {
+                    return Err(format!("Unknown map in recording: {}", recording.map_id()));
+                }
+                options.mode = Mode::Playback(recording);
+            }
+            "--headless" => options.headless = Some(PathBuf::from(value)),
+            "--export" => options.export = Some(PathBuf::from(value)),
+            "--themes" => options.themes = Some(PathBuf::from(value)),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    if options.terminal && options.headless.is_some() {
+        return Err(USAGE.to_string());
+    }
+    Ok(options)
+}
+
+/// Switches to the theme after the current one, wrapping around.
+fn next_theme(renderer: &mut renderer::Renderer, themes: &[Theme]) {
+    let current = themes
+        .iter()
+        .position(|theme| theme.name == renderer.theme().name);
+    let next = current.map_or(0, |i| (i + 1) % themes.len());
+    if let Some(theme) = themes.get(next) {
+        renderer.set_theme(theme.clone());
+    }
+}
+
+/// What lies around on the built-in map, which has no markers to spawn
+/// entities from, see `Gamestate::load_map`.
+fn builtin_entities() -> Vec<Entity> {
+    let mut enemy = Entity::new((450.0, 450.0), 0);
+    enemy.health = Some(50.0);
+    enemy.behaviour = Behaviour::Enemy(EnemyAi::new(vec![(450.0, 450.0), (250.0, 450.0)]));
+
+    let pickup = |position, item| {
+        let mut pickup = Entity::new(position, 0);
+        pickup.behaviour = Behaviour::Pickup(item);
+        pickup
+    };
+    vec![
+        enemy,
+        pickup((250.0, 350.0), Item::Health(25.0)),
+        pickup((450.0, 150.0), Item::Ammo(10)),
+        pickup((450.0, 250.0), Item::Score(100)),
+    ]
+}
+
+fn terminal_renderer() -> Result<(renderer::Renderer, TerminalInput), String> {
+    let (columns, rows) = TerminalBackend::terminal_size().ok_or("Could not get terminal size")?;
+    // Every character shows two square pixels stacked on top of each other,
+    // one line is left free so the terminal does not scroll.
+    let aspect_ratio = WINDOW_WIDTH as f32 / WINDOW_HEIGHT as f32;
+    let rows = rows
+        .saturating_sub(1)
+        .min((columns as f32 / aspect_ratio / 2.0) as u32);
+    let columns = columns.min((rows as f32 * aspect_ratio * 2.0) as u32);
+
+    let input = TerminalInput::new()?;
+    let backend = TerminalBackend::new(WINDOW_WIDTH, WINDOW_HEIGHT, columns, rows);
+    Ok((renderer::Renderer::with_backend(Box::new(backend)), input))
+}
 
 pub fn main() {
-    let mut renderer = renderer::Renderer::new(1250, 500, "Sdl demo window");
+    let Options {
+        mode,
+        headless,
+        terminal,
+        software,
+        export,
+        themes,
+    } = match parse_options() {
+        Ok(options) => options,
+        Err(s) => {
+            println!("{s}");
+            return;
+        }
+    };
+
+    let (mut renderer, mut terminal_input) = if terminal {
+        match terminal_renderer() {
+            Ok((renderer, input)) => (renderer, Some(input)),
+            Err(s) => {
+                println!("Error setting up the terminal: {s}");
+                return;
+            }
+        }
+    } else if headless.is_some() {
+        (
+            renderer::Renderer::new_headless(WINDOW_WIDTH, WINDOW_HEIGHT),
+            None,
+        )
+    } else {
+        match renderer::Renderer::try_new(WINDOW_WIDTH, WINDOW_HEIGHT, "Sdl demo window") {
+            Ok(renderer) => (renderer, None),
+            Err(e) => {
+                println!("{e}");
+                return;
+            }
+        }
+    };
 
     let map = [
         [true, true, true, true, true, true],
@@ -15,18 +190,60 @@ pub fn main() {
         [true, true, true, true, true, true],
     ]
     .iter()
-    .map(|row| Vec::from(row))
+    .map(Vec::from)
     .collect::<Vec<Vec<bool>>>();
 
     let mut gamestate = gamestate::Gamestate::new(map, 150.0, 150.0, 100, 275);
+    for entity in builtin_entities() {
+        gamestate.spawn_entity(entity);
+    }
 
-    renderer.set_background_color(sdl2::pixels::Color::RGB(0, 0, 0));
-    renderer.set_wall_color(sdl2::pixels::Color::RGB(147, 151, 153));
-    renderer.set_floor_color(sdl2::pixels::Color::RGB(52, 140, 49));
-    renderer.set_player_color(sdl2::pixels::Color::RGB(0, 0, 255));
-    renderer.set_ray_color(sdl2::pixels::Color::RGB(0, 191, 255));
-    renderer.set_sky_color(sdl2::pixels::Color::RGB(135, 206, 235));
+    let seed = match &mode {
+        Mode::Playback(recording) => recording.seed(),
+        _ => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0),
+    };
+    gamestate.set_seed(seed);
+    gamestate.set_diagnostics_hook(|diagnostic| {
+        if diagnostic.level() >= Level::Warning {
+            println!("{diagnostic}");
+        }
+    });
 
+    let mut recording = match &mode {
+        Mode::Record(_) => Some(Recording::new(seed, MAP_ID)),
+        _ => None,
+    };
+    let mut playback_ticks = match &mode {
+        Mode::Playback(recording) => Some(recording.ticks().iter()),
+        _ => None,
+    };
+
+    let mut themes_available = Theme::builtin();
+    if let Some(path) = themes {
+        match Theme::load(&path) {
+            Ok(themes) => themes_available.extend(themes),
+            Err(s) => {
+                println!("Error loading themes: {s}");
+                return;
+            }
+        }
+    }
+
+    let mut frame_recorder = match export {
+        Some(path) => match FrameRecorder::new(&path, FrameRecorder::DEFAULT_FRAME_INTERVAL) {
+            Ok(recorder) => Some(recorder),
+            Err(s) => {
+                println!("Error starting frame export: {s}");
+                return;
+            }
+        },
+        None => None,
+    };
+
+    renderer.set_software_rendering(software);
     match renderer.set_scale(1.5) {
         Ok(_) => {}
         Err(s) => {
@@ -34,15 +251,23 @@ pub fn main() {
         }
     }
 
-    let mut event_pump = renderer.event_pump();
+    // Only a window has an event pump, the terminal has its own input and a
+    // headless renderer only ever plays back recordings.
+    let mut event_pump = renderer.event_pump().ok();
 
     struct EventWrapper {
         w: bool,
         s: bool,
         a: bool,
         d: bool,
+        fire: bool,
+        use_door: bool,
 
         last_event: std::time::Instant,
+        screenshot: bool,
+        toggle_clip: bool,
+        save_game: bool,
+        load_game: bool,
     }
 
     let mut events = EventWrapper {
@@ -50,12 +275,63 @@ pub fn main() {
         s: false,
         a: false,
         d: false,
+        fire: false,
+        use_door: false,
 
         last_event: std::time::Instant::now(),
+        screenshot: false,
+        toggle_clip: false,
+        save_game: false,
+        load_game: false,
     };
+    let mut save_slot = 1;
 
     'running: loop {
-        for event in event_pump.poll_iter() {
+        for event in event_pump.iter_mut().flat_map(|pump| pump.poll_iter()) {
+            if renderer.console().is_open() {
+                match event {
+                    Event::Quit { .. } => break 'running,
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Escape | Keycode::Backquote),
+                        ..
+                    } => renderer.console_mut().set_open(false),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Return | Keycode::KpEnter),
+                        ..
+                    } => {
+                        if let Some(line) = renderer.console_mut().submit() {
+                            let can_change_game = matches!(mode, Mode::Interactive);
+                            let output = commands::execute(
+                                &line,
+                                &mut gamestate,
+                                &mut renderer,
+                                &themes_available,
+                                can_change_game,
+                            )
+                            .unwrap_or_else(|s| s);
+                            renderer.console_mut().print(&output);
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Backspace),
+                        ..
+                    } => renderer.console_mut().backspace(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Up),
+                        ..
+                    } => renderer.console_mut().history_previous(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Down),
+                        ..
+                    } => renderer.console_mut().history_next(),
+                    Event::TextInput { text, .. } => {
+                        renderer.console_mut().type_text(&text.replace('`', ""))
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
             match event {
                 Event::Quit { .. }
                 | Event::KeyDown {
@@ -63,10 +339,51 @@ pub fn main() {
                     ..
                 } => break 'running,
                 Event::KeyDown { keycode: code, .. } => match code {
+                    Some(Keycode::F12) => events.screenshot = true,
+                    Some(Keycode::F9) => events.toggle_clip = true,
+                    Some(Keycode::F5) => events.save_game = true,
+                    Some(Keycode::F8) => events.load_game = true,
+                    Some(Keycode::F6) => {
+                        save_slot = save_slot % SAVE_SLOTS + 1;
+                        println!("Save slot {save_slot}");
+                    }
+                    Some(Keycode::M) => cycle_minimap_mode(renderer.minimap_mut()),
+                    Some(Keycode::Tab) => renderer.automap_mut().toggle(),
+                    Some(Keycode::Up) => renderer.automap_mut().pan(0.0, -1.0),
+                    Some(Keycode::Down) => renderer.automap_mut().pan(0.0, 1.0),
+                    Some(Keycode::Left) => renderer.automap_mut().pan(-1.0, 0.0),
+                    Some(Keycode::Right) => renderer.automap_mut().pan(1.0, 0.0),
+                    Some(Keycode::Equals | Keycode::KpPlus) => renderer.automap_mut().zoom(1.25),
+                    Some(Keycode::Minus | Keycode::KpMinus) => renderer.automap_mut().zoom(0.8),
+                    Some(Keycode::Home) => renderer.automap_mut().reset(),
+                    Some(Keycode::LeftBracket) => {
+                        let minimap = renderer.minimap_mut();
+                        minimap.set_zoom(minimap.zoom() / 1.25);
+                    }
+                    Some(Keycode::RightBracket) => {
+                        let minimap = renderer.minimap_mut();
+                        minimap.set_zoom(minimap.zoom() * 1.25);
+                    }
+                    Some(Keycode::F3) => renderer.debug_hud_mut().toggle(),
+                    Some(Keycode::F4) => next_theme(&mut renderer, &themes_available),
+                    Some(Keycode::Backquote) => {
+                        renderer.console_mut().set_open(true);
+                        // The console takes the keyboard, so nothing stays held.
+                        events.w = false;
+                        events.s = false;
+                        events.a = false;
+                        events.d = false;
+                        events.fire = false;
+                    }
+                    Some(Keycode::F2) => {
+                        renderer.set_software_rendering(!renderer.software_rendering())
+                    }
                     Some(Keycode::W) => events.w = true,
                     Some(Keycode::S) => events.s = true,
                     Some(Keycode::A) => events.a = true,
                     Some(Keycode::D) => events.d = true,
+                    Some(Keycode::Space) => events.fire = true,
+                    Some(Keycode::E) => events.use_door = true,
                     _ => {}
                 },
                 Event::KeyUp { keycode: code, .. } => match code {
@@ -74,78 +391,186 @@ pub fn main() {
                     Some(Keycode::S) => events.s = false,
                     Some(Keycode::A) => events.a = false,
                     Some(Keycode::D) => events.d = false,
+                    Some(Keycode::Space) => events.fire = false,
                     _ => {}
                 },
 
                 _ => {}
             }
         }
+        if let Some(input) = terminal_input.as_mut() {
+            for key in input.poll() {
+                if key == TerminalKey::Escape || key == TerminalKey::Char('q') {
+                    break 'running;
+                }
+                match key {
+                    TerminalKey::Char('e') => events.use_door = true,
+                    TerminalKey::Char('p') => events.screenshot = true,
+                    _ => {}
+                }
+            }
+            events.w = input.is_held(TerminalKey::Char('w'));
+            events.s = input.is_held(TerminalKey::Char('s'));
+            events.a = input.is_held(TerminalKey::Char('a'));
+            events.d = input.is_held(TerminalKey::Char('d'));
+            events.fire = input.is_held(TerminalKey::Char(' '));
+        }
+
+        if events.save_game {
+            events.save_game = false;
+            save_game(&gamestate, save_slot);
+        }
+        if events.load_game {
+            events.load_game = false;
+            if matches!(mode, Mode::Interactive) {
+                load_game(&mut gamestate, save_slot);
+            } else {
+                println!("Loading a save is not available while recording or playing back");
+            }
+        }
 
         let current_time = std::time::Instant::now();
-        let delta_time = (current_time - events.last_event).as_nanos();
+        let previous_frame = events.last_event;
+        let delta_time = (current_time - previous_frame).as_nanos();
 
         events.last_event = current_time;
-        if events.w {
-            gamestate.player_move(MoveDirection::Forward, delta_time);
-        }
-        if events.s {
-            gamestate.player_move(MoveDirection::Backward, delta_time);
+        renderer
+            .debug_hud_mut()
+            .record_frame(current_time - previous_frame);
+        renderer
+            .damage_flash_mut()
+            .fade(current_time - previous_frame);
+        renderer.gunfire_mut().fade(current_time - previous_frame);
+
+        let tick = match playback_ticks.as_mut() {
+            Some(ticks) => match ticks.next() {
+                Some(tick) => tick.clone(),
+                None => break 'running,
+            },
+            None => {
+                let mut tick = TickInput::new(delta_time);
+                if events.w {
+                    tick.actions.push(Action::MoveForward);
+                }
+                if events.s {
+                    tick.actions.push(Action::MoveBackward);
+                }
+                if events.a {
+                    tick.actions.push(Action::TurnLeft);
+                }
+                if events.d {
+                    tick.actions.push(Action::TurnRight);
+                }
+                if events.fire {
+                    tick.actions.push(Action::Fire);
+                }
+                if events.use_door {
+                    events.use_door = false;
+                    tick.actions.push(Action::Use);
+                }
+                tick
+            }
+        };
+        gamestate.apply_input(&tick);
+        for shot in gamestate.take_shots() {
+            let impact = match shot.hit {
+                Hit::Nothing => Impact::Nothing,
+                Hit::Wall => Impact::Wall,
+                Hit::Entity { .. } => Impact::Entity,
+            };
+            renderer.gunfire_mut().fire(shot.point, impact);
         }
-        if events.a {
-            gamestate.player_rotate(TurnDirection::Left, delta_time);
+        for event in gamestate.take_events() {
+            match event {
+                GameEvent::Damaged {
+                    target: Target::Player,
+                    amount,
+                    ..
+                } => renderer.damage_flash_mut().hit(amount),
+                GameEvent::Died {
+                    target: Target::Player,
+                    ..
+                } => renderer.console_mut().print("You died"),
+                GameEvent::Died {
+                    target: Target::Entity(id),
+                    ..
+                } => renderer
+                    .console_mut()
+                    .print(&format!("Entity {} destroyed", id.0)),
+                GameEvent::DoorLocked { key } => renderer
+                    .console_mut()
+                    .print(&format!("This door needs key {key}")),
+                _ => {}
+            }
         }
-        if events.d {
-            gamestate.player_rotate(TurnDirection::Right, delta_time);
+        let tick_duration = std::time::Duration::from_nanos(tick.delta_time as u64);
+        if let Some(recording) = recording.as_mut() {
+            recording.push(tick);
         }
 
         // The rest of the game loop goes here...
 
-        renderer.clear_canvas();
-        let map_drawing_res = renderer.draw_map(
-            gamestate.map_walls(),
-            gamestate.map_width(),
-            gamestate.map_height(),
-            35,
-        );
-        match map_drawing_res {
+        match renderer.clear_canvas() {
             Ok(_) => {}
             Err(s) => {
                 println!("Unsuccessful drawing: {s}")
             }
         }
-
         let (x, y) = gamestate.player_position();
 
+        let draw_start = std::time::Instant::now();
         let rays = gamestate.cast_rays();
-        let rays_drawing_res = renderer.draw_rays(
-            model_to_map_coordinate(x, y, gamestate.block_size()),
-            rays.iter()
-                .map(|(x_ray, y_ray, _)| {
-                    model_to_map_coordinate(*x_ray, *y_ray, gamestate.block_size())
-                })
-                .collect::<Vec<sdl2::rect::Point>>(),
-        );
-        match rays_drawing_res {
+        let cast_time = draw_start.elapsed();
+        gamestate.explore(&rays);
+        let ray_ends = rays
+            .iter()
+            .map(|(x_ray, y_ray, _)| (*x_ray, *y_ray))
+            .collect::<Vec<(f32, f32)>>();
+        let entity_markers = gamestate
+            .entities()
+            .iter()
+            .map(|(_, entity)| EntityMarker {
+                position: entity.position,
+                radius: entity.radius,
+                kind: match entity.behaviour {
+                    Behaviour::Enemy(_) => EntityKind::Enemy,
+                    Behaviour::Pickup(_) => EntityKind::Pickup,
+                    Behaviour::Static | Behaviour::Spin { .. } => EntityKind::Prop,
+                },
+            })
+            .collect::<Vec<EntityMarker>>();
+        let doors = gamestate
+            .doors()
+            .iter()
+            .map(|(cell, door)| (*cell, !door.blocks()))
+            .collect::<Vec<(u16, bool)>>();
+        let push_walls = gamestate.push_wall_cells();
+        let map_view = MapView {
+            walls: gamestate.map_walls(),
+            width: gamestate.map_width(),
+            height: gamestate.map_height(),
+            block_size: gamestate.block_size(),
+            explored: Some(gamestate.explored_cells()),
+            push_walls: &push_walls,
+            doors: &doors,
+            entities: &entity_markers,
+        };
+        let player_view = PlayerView {
+            position: (x, y),
+            rotation: gamestate.player_rotation(),
+            ray_ends: &ray_ends,
+            shot: renderer.gunfire().point(),
+        };
+        let minimap_drawing_res = renderer.draw_minimap(&map_view, &player_view);
+        match minimap_drawing_res {
             Ok(_) => {}
             Err(s) => {
                 println!("Unsuccessful drawing: {s}")
             }
         }
 
-        let player_drawing_res = renderer.draw_player(
-            model_to_map_coordinate(x, y, gamestate.block_size()),
-            gamestate.player_rotation(),
-        );
-        match player_drawing_res {
-            Ok(_) => {}
-            Err(s) => {
-                println!("Unsuccessful drawing: {s}")
-            }
-        }
-        let screen_drawing_res = renderer.draw_screen(
-            sdl2::rect::Point::new(250, 0),
-            sdl2::rect::Point::new(800, 300),
-        );
+        let screen_drawing_res =
+            renderer.draw_screen(renderer::Point::new(250, 0), renderer::Point::new(800, 300));
 
         match screen_drawing_res {
             Ok(_) => {}
@@ -154,17 +579,18 @@ pub fn main() {
             }
         }
 
-        println!("\n\n");
+        let materials = gamestate.hit_materials(&rays);
         let ray_lengths = rays
             .iter()
-            .map(|(ray_x, ray_y, hits_wall)| {
+            .zip(materials)
+            .map(|((ray_x, ray_y, hits_wall), material)| {
                 let x_diff = (ray_x - x).powi(2);
                 let y_diff = (ray_y - y).powi(2);
 
                 let dist = 1.0 - (x_diff + y_diff).sqrt() / gamestate.view_distance();
-                (dist, *hits_wall)
+                (dist, *hits_wall, material)
             })
-            .collect::<Vec<(f32, bool)>>();
+            .collect::<Vec<(f32, bool, u8)>>();
         let walls_drawing_res = renderer.draw_walls(ray_lengths);
         match walls_drawing_res {
             Ok(_) => {}
@@ -172,13 +598,193 @@ pub fn main() {
                 println!("Unsuccessful drawing: {s}")
             }
         }
-        renderer.present_canvas();
+        match renderer.draw_gunfire() {
+            Ok(_) => {}
+            Err(s) => {
+                println!("Unsuccessful drawing: {s}")
+            }
+        }
+        match renderer.draw_damage_flash() {
+            Ok(_) => {}
+            Err(s) => {
+                println!("Unsuccessful drawing: {s}")
+            }
+        }
+        let status = Status {
+            health: gamestate.player_health(),
+            armor: gamestate.player_armor(),
+            ammo: gamestate.weapon().ammo,
+            score: gamestate.inventory().score,
+            keys: gamestate.inventory().keys.iter().copied().collect(),
+        };
+        match renderer.draw_status(&status) {
+            Ok(_) => {}
+            Err(s) => {
+                println!("Unsuccessful drawing: {s}")
+            }
+        }
+        match renderer.draw_automap(&map_view, &player_view) {
+            Ok(_) => {}
+            Err(s) => {
+                println!("Unsuccessful drawing: {s}")
+            }
+        }
+
+        let debug_stats = DebugStats {
+            player_position: (x, y),
+            player_rotation: gamestate.player_rotation(),
+            player_cell: gamestate.player_cell(),
+            ray_count: rays.len(),
+            cast_time,
+            draw_time: draw_start.elapsed() - cast_time,
+        };
+        match renderer.draw_debug_hud(&debug_stats) {
+            Ok(_) => {}
+            Err(s) => {
+                println!("Unsuccessful drawing: {s}")
+            }
+        }
+        match renderer.draw_console() {
+            Ok(_) => {}
+            Err(s) => {
+                println!("Unsuccessful drawing: {s}")
+            }
+        }
+
+        if events.screenshot {
+            events.screenshot = false;
+            let screenshot = std::fs::create_dir_all(SCREENSHOT_DIR)
+                .map_err(|e| {
+                    RendererError::Asset(format!("Could not create {SCREENSHOT_DIR}: {e}"))
+                })
+                .and_then(|_| renderer.screenshot(std::path::Path::new(SCREENSHOT_DIR)));
+            match screenshot {
+                Ok(path) => println!("Screenshot saved to {}", path.display()),
+                Err(s) => println!("Error taking screenshot: {s}"),
+            }
+        }
+        if events.toggle_clip {
+            events.toggle_clip = false;
+            frame_recorder = match frame_recorder.take() {
+                Some(recorder) => {
+                    finish_clip(recorder);
+                    None
+                }
+                None => start_clip(),
+            };
+        }
+        if let Some(recorder) = frame_recorder.as_mut() {
+            if let Err(s) = recorder.capture(&mut renderer, tick_duration) {
+                println!("Error capturing frame: {s}");
+                frame_recorder = None;
+            }
+        }
+        match renderer.present_canvas() {
+            Ok(_) => {}
+            Err(s) => {
+                println!("Unsuccessful drawing: {s}")
+            }
+        }
+
+        if headless.is_some() && playback_ticks.is_none() {
+            break 'running;
+        }
+    }
+
+    if let (Some(path), Some(framebuffer)) = (&headless, renderer.framebuffer()) {
+        match framebuffer.save(path) {
+            Ok(_) => println!("Frame written to {}", path.display()),
+            Err(s) => println!("Error writing frame: {s}"),
+        }
+    }
+    // Give the terminal back before printing anything else.
+    drop(terminal_input);
+    drop(renderer);
+
+    if let Some(recorder) = frame_recorder {
+        finish_clip(recorder);
+    }
+
+    match (&mode, recording.as_mut()) {
+        (Mode::Record(path), Some(recording)) => {
+            recording.finish(&gamestate);
+            match recording.save(path) {
+                Ok(_) => println!("Recording saved to {}", path.display()),
+                Err(s) => println!("Error saving recording: {s}"),
+            }
+        }
+        (Mode::Playback(recording), _) => {
+            let remaining = playback_ticks.map(|ticks| ticks.len()).unwrap_or(0);
+            if remaining > 0 {
+                println!("Playback interrupted with {remaining} ticks left");
+            } else {
+                match recording.verify(&gamestate) {
+                    Ok(_) => println!("Playback finished"),
+                    Err(s) => println!("{s}"),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Fixed, following the player, following and rotating with the player.
+fn cycle_minimap_mode(minimap: &mut Minimap) {
+    match (minimap.follow_player(), minimap.rotate_with_player()) {
+        (false, _) => minimap.set_follow_player(true),
+        (true, false) => minimap.set_rotate_with_player(true),
+        (true, true) => {
+            minimap.set_rotate_with_player(false);
+            minimap.set_follow_player(false);
+        }
+    }
+}
+
+fn save_path(slot: u32) -> PathBuf {
+    PathBuf::from(SAVE_DIR).join(format!("slot{slot}.sav"))
+}
+
+fn save_game(gamestate: &gamestate::Gamestate, slot: u32) {
+    let path = save_path(slot);
+    let saved = std::fs::create_dir_all(SAVE_DIR)
+        .map_err(|e| e.to_string())
+        .and_then(|_| gamestate.save_game(&path));
+    match saved {
+        Ok(_) => println!("Saved to slot {slot} ({})", path.display()),
+        Err(s) => println!("Error saving: {s}"),
     }
 }
 
-pub fn model_to_map_coordinate(x: f32, y: f32, gamestate_scale: u16) -> sdl2::rect::Point {
-    let x_screen = x / (gamestate_scale as f32);
-    let y_screen = y / (gamestate_scale as f32);
+fn load_game(gamestate: &mut gamestate::Gamestate, slot: u32) {
+    match gamestate.load_game(&save_path(slot)) {
+        Ok(_) => println!("Loaded slot {slot}"),
+        Err(s) => println!("Error loading: {s}"),
+    }
+}
+
+fn start_clip() -> Option<FrameRecorder> {
+    let path =
+        PathBuf::from(CLIP_DIR).join(renderer::timestamped_file_name("clip", ImageFormat::Gif));
+    let recorder = std::fs::create_dir_all(CLIP_DIR)
+        .map_err(|e| RendererError::Asset(format!("Could not create {CLIP_DIR}: {e}")))
+        .and_then(|_| FrameRecorder::new(&path, FrameRecorder::DEFAULT_FRAME_INTERVAL));
+    match recorder {
+        Ok(recorder) => {
+            println!("Recording clip to {}", path.display());
+            Some(recorder)
+        }
+        Err(s) => {
+            println!("Error starting clip: {s}");
+            None
+        }
+    }
+}
 
-    sdl2::rect::Point::new((x_screen * 35.0) as i32, (y_screen * 35.0) as i32)
+fn finish_clip(recorder: FrameRecorder) {
+    let path = recorder.path().to_path_buf();
+    let frames = recorder.frames();
+    match recorder.finish() {
+        Ok(_) => println!("Saved {frames} frames to {}", path.display()),
+        Err(s) => println!("Error finishing clip: {s}"),
+    }
 }